@@ -2,8 +2,22 @@ mod merge;
 mod generate;
 mod filter;
 mod merge_har;
+mod diff;
+mod validate;
+mod coverage;
+mod mock;
+mod replay;
+mod record;
+mod emit;
 
 pub use merge::*;
 pub use generate::*;
 pub use filter::*;
-pub use merge_har::*;
\ No newline at end of file
+pub use merge_har::*;
+pub use diff::*;
+pub use validate::*;
+pub use coverage::*;
+pub use mock::*;
+pub use replay::*;
+pub use record::*;
+pub use emit::*;
\ No newline at end of file
@@ -0,0 +1,68 @@
+use crate::http::{read_har, RequestResponse};
+use crate::openapi::coverage;
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use openapiv3::OpenAPI;
+use serde_json::json;
+use std::fs::File;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CoverageFormat {
+    Text,
+    Json,
+    /// lcov tracefile, with one record per operation
+    Lcov,
+}
+
+#[derive(Debug, Args)]
+pub struct Coverage {
+    spec: String,
+
+    /// HAR files to measure. Can be repeated.
+    #[arg(num_args = 1.., required = true)]
+    har_files: Vec<String>,
+
+    #[arg(long, value_enum, default_value = "text")]
+    format: CoverageFormat,
+
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+impl Coverage {
+    pub fn run(self) -> Result<()> {
+        let spec: OpenAPI = serde_yaml::from_reader(File::open(&self.spec)?)?;
+        let mut rrs = Vec::new();
+        let mut unnamed = 0;
+        for path in &self.har_files {
+            for entry in read_har(path)? {
+                match RequestResponse::try_from_entry(entry) {
+                    Some(rr) => rrs.push(rr),
+                    None => unnamed += 1,
+                }
+            }
+        }
+        let mut coverage = coverage::coverage(&spec, &rrs);
+        // Requests like `GET /` can't be matched to an operation.
+        coverage.unmatched += unnamed;
+        let output = match self.format {
+            CoverageFormat::Text => coverage.to_text(),
+            CoverageFormat::Json => {
+                let doc = json!({
+                    "summary": coverage.summary(),
+                    "operations": coverage.operations,
+                    "unmatched": coverage.unmatched,
+                });
+                serde_json::to_string_pretty(&doc)? + "\n"
+            }
+            CoverageFormat::Lcov => coverage.to_lcov(),
+        };
+        if let Some(path) = self.output {
+            std::fs::write(&path, &output)?;
+            eprintln!("{}: Wrote file.", path);
+        } else {
+            print!("{}", output);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::command::read_spec;
+use crate::openapi::diff;
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// The old spec. A `.har` file is compared by generating a spec from it first.
+    old: String,
+
+    /// The new spec, or a `.har` file.
+    new: String,
+
+    #[arg(long, value_enum, default_value = "markdown")]
+    format: DiffFormat,
+
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Exit with an error when any change is breaking
+    #[arg(long)]
+    fail_on_breaking: bool,
+}
+
+impl Diff {
+    pub fn run(self) -> Result<()> {
+        let old = read_spec(&self.old)?;
+        let new = read_spec(&self.new)?;
+        let changes = diff::diff_specs(&old, &new);
+        let output = match self.format {
+            DiffFormat::Markdown => diff::to_markdown(&changes),
+            DiffFormat::Json => serde_json::to_string_pretty(&changes)? + "\n",
+        };
+        if let Some(path) = self.output {
+            std::fs::write(&path, &output)?;
+            eprintln!("{}: Wrote file.", path);
+        } else {
+            print!("{}", output);
+        }
+        let breaking = changes.iter().filter(|c| c.breaking).count();
+        if self.fail_on_breaking && breaking > 0 {
+            bail!("{} breaking changes", breaking);
+        }
+        Ok(())
+    }
+}
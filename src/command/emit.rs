@@ -0,0 +1,53 @@
+use crate::command::read_spec;
+use crate::emit;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+#[derive(Debug, Args)]
+pub struct EmitArgs {
+    /// A HAR file to infer the schemas from, or an OpenAPI spec
+    input: String,
+
+    /// Output file. Defaults to stdout.
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EmitTarget {
+    /// serde structs and enums
+    Rust(EmitArgs),
+    /// TypeScript interfaces, plus request and response types per operation
+    Typescript(EmitArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct Emit {
+    #[command(subcommand)]
+    target: EmitTarget,
+}
+
+impl Emit {
+    pub fn run(self) -> Result<()> {
+        let (args, code) = match self.target {
+            EmitTarget::Rust(args) => {
+                let spec = read_spec(&args.input)?;
+                let code = emit::rust::emit_rust(&spec);
+                (args, code)
+            }
+            EmitTarget::Typescript(args) => {
+                let spec = read_spec(&args.input)?;
+                let code = emit::typescript::emit_typescript(&spec);
+                (args, code)
+            }
+        };
+        match args.output {
+            Some(path) => {
+                std::fs::write(&path, code)?;
+                eprintln!("{}: Wrote file.", path);
+            }
+            None => print!("{}", code),
+        }
+        Ok(())
+    }
+}
@@ -1,38 +1,213 @@
-use crate::http::{read_har, RequestResponse};
+use crate::http::{read_har_log, RequestResponse};
 use crate::openapi;
-use crate::openapi::{operation, response};
+use crate::openapi::example::{self, Redactor};
+use crate::openapi::operation::OperationOptions;
+use crate::openapi::{operation, response, schema, security, update};
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, FromArgMatches, ValueEnum};
 use har::v1_2::Entries;
 use indexmap::indexmap;
+use convert_case::{Case, Casing};
 use itertools::Itertools;
 use openapiv3 as oa;
 use openapiv3::ReferenceOr;
+use serde::Deserialize;
 use std::fs;
-use tracing::debug;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OpenApiVersion {
+    /// Swagger 2.0
+    #[value(name = "2.0")]
+    V2_0,
+    #[value(name = "3.0")]
+    V3_0,
+    #[value(name = "3.1")]
+    V3_1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// `.json` outputs are JSON, everything else (including stdout) is YAML.
+    fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".json") {
+            Format::Json
+        } else {
+            Format::Yaml
+        }
+    }
+}
+
+/// Settings that are too verbose for flags, read from the `--config` YAML file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Config {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub terms_of_service: Option<String>,
+    pub contact: Option<oa::Contact>,
+    pub license: Option<oa::License>,
+}
 
 #[derive(Debug, Args)]
 pub struct Generate {
     pub har_file: String,
-    /// Add cookie authentication. E.g. `--cookie sessid` adds a security schema where a cookie named `sessid` is required
+    /// Name of the session cookie, e.g. `--cookie sessid`. By default, cookies that look like session cookies
+    /// (e.g. `sessionid`, `connect.sid`) are detected.
     #[clap(long)]
     pub cookie: Option<String>,
+    /// Output file, or `-` for stdout. Defaults to the `--update` file, or `openapi.yaml`.
     #[clap(short, long)]
     pub output: Option<String>,
+    /// Output format. Inferred from the output file extension if not given.
+    #[clap(long, value_enum)]
+    pub format: Option<Format>,
+    /// Factor properties shared by many schemas (e.g. `id`, `created_at`) into base schemas, referenced with `allOf`
+    #[clap(long)]
+    pub extract_base_schemas: bool,
+    /// Minimum number of shared properties for `--extract-base-schemas` to create a base schema
+    #[clap(long, default_value_t = 3, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub min_group_size: usize,
+    /// Don't attach captured values as examples
+    #[clap(long)]
+    pub no_examples: bool,
+    /// Redact values of this key (matched case-insensitively as a substring) in examples, in addition to
+    /// built-in ones like `password` and `token`. Can be repeated.
+    #[clap(long)]
+    pub redact: Vec<String>,
+    /// Don't document this header, as a request header parameter or as a response header (case-insensitive).
+    /// Can be repeated.
+    #[clap(long)]
+    pub ignore_header: Vec<String>,
+    /// Leave OAuth2 and OpenID Connect endpoints (authorize, token, discovery) out of the paths. They are still
+    /// used to detect the security schemes.
+    #[clap(long)]
+    pub exclude_auth_endpoints: bool,
+    /// OpenAPI version of the output. 3.1 uses JSON Schema 2020-12 (`type: [string, "null"]`, `examples`, `const`).
+    /// 2.0 exports to Swagger 2.0, warning about anything that can't be represented.
+    #[clap(long, value_enum, default_value = "3.0")]
+    pub openapi_version: OpenApiVersion,
+    /// Title of the API. Defaults to one derived from the host, e.g. `Example API` for `api.example.com`.
+    #[clap(long)]
+    pub title: Option<String>,
+    /// Version of the API. Defaults to the date of the capture.
+    #[clap(long)]
+    pub version: Option<String>,
+    /// Description of the API. Defaults to one naming the tool that recorded the capture.
+    #[clap(long)]
+    pub description: Option<String>,
+    /// YAML file with `title`, `version`, `description`, `termsOfService`, `contact` and `license` for the info
+    /// section. Flags take precedence.
+    #[clap(long)]
+    pub config: Option<String>,
+    /// Merge what was observed into this existing spec instead of starting from scratch. Nothing already in it is
+    /// removed or overwritten, and every addition is reported. Only OpenAPI 3.0 specs can be updated.
+    #[clap(long)]
+    pub update: Option<String>,
 }
 
 impl Generate {
+    /// Generate with the default settings, e.g. to compare a HAR file against a spec.
+    pub fn with_defaults(har_file: &str) -> Result<Self> {
+        let command = Self::augment_args(clap::Command::new("generate"));
+        let matches = command.try_get_matches_from(["generate", har_file])?;
+        Ok(Self::from_arg_matches(&matches)?)
+    }
+
     pub fn run(self) -> Result<()> {
-        let hars: Vec<Entries> = read_har(&self.har_file)?;
-        let mut rrs: Vec<RequestResponse> = hars
+        if let Some(path) = &self.update {
+            // The spec is written back in place, so it has to stay in the version it was read in.
+            anyhow::ensure!(
+                self.openapi_version == OpenApiVersion::V3_0,
+                "{}: --update only writes OpenAPI 3.0 specs, drop --openapi-version",
+                path
+            );
+        }
+        let mut schema = self.create_spec()?;
+        if let Some(path) = &self.update {
+            let mut existing: oa::OpenAPI = serde_yaml::from_reader(fs::File::open(path)?)?;
+            anyhow::ensure!(
+                existing.openapi.starts_with("3.0"),
+                "{}: --update only supports OpenAPI 3.0 specs, found {}",
+                path,
+                existing.openapi
+            );
+            let added = update::update_spec(&mut existing, schema);
+            for addition in &added {
+                eprintln!("{}: {}", path, addition);
+            }
+            if added.is_empty() {
+                eprintln!("{}: No new paths, parameters or properties.", path);
+            }
+            schema = existing;
+        }
+
+        let doc = match self.openapi_version {
+            OpenApiVersion::V2_0 => {
+                let (doc, warnings) = openapi::v2::to_swagger2(&schema)?;
+                for warning in warnings {
+                    warn!("Swagger 2.0: {}", warning);
+                }
+                doc
+            }
+            OpenApiVersion::V3_0 => serde_json::to_value(&schema)?,
+            OpenApiVersion::V3_1 => openapi::v3_1::to_v3_1(&schema)?,
+        };
+        let path = self.output.as_deref().or(self.update.as_deref()).unwrap_or("openapi.yaml");
+        let s = match self.format.unwrap_or_else(|| Format::from_path(path)) {
+            Format::Json => serde_json::to_string_pretty(&doc)? + "\n",
+            Format::Yaml => serde_yaml::to_string(&doc)?,
+        };
+        if path == "-" {
+            print!("{}", s);
+        } else {
+            fs::write(path, &s)?;
+            eprintln!("{}: Wrote file.", path);
+        }
+        Ok(())
+    }
+
+    /// Create the OpenAPI 3.0 spec for the HAR file.
+    pub fn create_spec(&self) -> Result<oa::OpenAPI> {
+        let config = match &self.config {
+            Some(path) => serde_yaml::from_reader(fs::File::open(path)?)?,
+            None => Config::default(),
+        };
+        let log = read_har_log(&self.har_file)?;
+        // The date part of the first request's ISO 8601 timestamp.
+        let captured = log.entries
+            .iter()
+            .map(|e| e.started_date_time.as_str())
+            .min()
+            .map(|d| d.chars().take(10).collect::<String>());
+        let creator = log.creator;
+        let total = log.entries.len();
+        let mut rrs: Vec<RequestResponse> = log.entries
             .into_iter()
-            .map(|h| h.into())
+            // Requests without an object name in the path, e.g. `GET /`, can't become an operation.
+            .filter_map(RequestResponse::try_from_entry)
             // .unique_by(|rr: &RequestResponse| rr.request.url.path().to_string())
             .collect::<Vec<_>>();
+        if rrs.len() < total {
+            warn!(n = total - rrs.len(), "Skipped requests without an object name in the path");
+        }
 
         rrs.sort_by_key(|rr| rr.request.url.path().to_string());
         debug!(n = rrs.len(), "Read har requests");
 
+        // Detect security before the auth endpoints are excluded, since they describe the flows.
+        let security_schemes = security::create_security_schemes(&rrs, self.cookie.as_deref());
+        let bearer_scheme = security::bearer_scheme(&rrs);
+        if self.exclude_auth_endpoints {
+            rrs.retain(|rr| !security::is_oauth_exchange(rr));
+        }
+
         let server = {
             let urls = rrs
                 .iter()
@@ -44,10 +219,21 @@ impl Generate {
             }
             server
         };
-        let security_schema_name = "Session".to_string();
+        let info = oa::Info {
+            title: self.title.clone().or(config.title).unwrap_or_else(|| default_title(&server)),
+            description: self.description.clone().or(config.description).or_else(|| {
+                let date = captured.as_ref().map(|d| format!(" on {}", d)).unwrap_or_default();
+                Some(format!("Generated from a capture recorded with {} {}{}.", creator.name, creator.version, date))
+            }),
+            terms_of_service: config.terms_of_service,
+            contact: config.contact,
+            license: config.license,
+            version: self.version.clone().or(config.version).or(captured).unwrap_or_else(|| "0.1.0".to_string()),
+            extensions: Default::default(),
+        };
         let mut schema = oa::OpenAPI {
             openapi: "3.0.3".to_string(),
-            info: Default::default(),
+            info,
             servers: vec![oa::Server {
                 url: server,
                 description: None,
@@ -62,33 +248,51 @@ impl Generate {
             extensions: Default::default(),
         };
 
-        response::create_schema_for_responses(&rrs, &mut schema.components)?;
-        operation::create_paths(&rrs, &mut schema.paths, &schema.servers.first().as_ref().unwrap().url);
-
-        if let Some(cookie) = self.cookie {
-            schema.security = vec![indexmap! {
-                security_schema_name.clone() => vec![security_schema_name.clone()],
-            }];
-
-            schema.security_schemes.insert(
-                security_schema_name,
-                ReferenceOr::Item(oa::SecurityScheme::APIKey {
-                    location: oa::APIKeyLocation::Cookie,
-                    name: cookie,
-                    description: None,
-                }),
-            );
+        let options = OperationOptions {
+            redactor: (!self.no_examples).then(|| Redactor::new(&self.redact)),
+            ignore_headers: self.ignore_header.clone(),
+            session_cookie: self.cookie.clone(),
+            bearer_scheme,
         };
+        for (name, scheme) in security_schemes {
+            schema.security_schemes.insert(name, ReferenceOr::Item(scheme));
+        }
+        response::create_schema_for_responses(&rrs, &mut schema.components)?;
+        example::redact_schema_examples(&mut schema.components, options.redactor.as_ref());
+        if self.extract_base_schemas {
+            schema::extract_base_schemas(&mut schema.components, self.min_group_size);
+        }
+        operation::create_paths(&rrs, &mut schema.paths, &schema.servers.first().as_ref().unwrap().url, &options)?;
+        Ok(schema)
+    }
+}
 
-        let s = serde_yaml::to_string(&schema)?;
-        let path = self.output.as_deref().unwrap_or("openapi.yaml");
-        // let path = "openapi.yaml";
-        fs::write(path, &s)?;
-        println!("{}: Wrote file.", path);
-        Ok(())
+/// Read a spec file, or generate one from a `.har` file with the default settings.
+pub(crate) fn read_spec(path: &str) -> Result<oa::OpenAPI> {
+    if path.to_ascii_lowercase().ends_with(".har") {
+        Generate::with_defaults(path)?.create_spec()
+    } else {
+        Ok(serde_yaml::from_reader(fs::File::open(path)?)?)
     }
 }
 
+/// A title from the server's host, without common prefixes like `api.`: `https://api.example.com`
+/// becomes `Example API`.
+fn default_title(server: &str) -> String {
+    let host = url::Url::parse(server)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let name = host
+        .split('.')
+        .find(|label| !["www", "api", "app"].contains(label))
+        .unwrap_or("");
+    if name.is_empty() || name.parse::<u8>().is_ok() {
+        return "API".to_string();
+    }
+    format!("{} API", name.to_case(Case::Title))
+}
+
 fn longest_common_prefix(strings: &[&str]) -> String {
     if strings.is_empty() {
         return String::new();
@@ -125,4 +329,26 @@ mod test {
             "https://app.studiodesigner.com/api/"
         );
     }
+
+    #[test]
+    fn test_default_title() {
+        assert_eq!(default_title("https://api.example.com/v1"), "Example API");
+        assert_eq!(default_title("https://app.studio-designer.com"), "Studio Designer API");
+        assert_eq!(default_title("http://127.0.0.1:8080"), "API");
+    }
+
+    #[test]
+    fn test_create_spec_skips_root_requests() -> Result<()> {
+        let entries = [
+            crate::http::test_entry("GET", "https://api.example.com/", serde_json::json!({})),
+            crate::http::test_entry("GET", "https://api.example.com/users", serde_json::json!([{"id": 1}])),
+        ];
+        let path = std::env::temp_dir().join(format!("har2oa-generate-{}.har", std::process::id()));
+        let path = path.to_str().unwrap();
+        crate::record::write_har(path, &entries)?;
+        let spec = Generate::with_defaults(path).and_then(|g| g.create_spec());
+        fs::remove_file(path)?;
+        assert_eq!(spec?.paths.paths.keys().collect::<Vec<_>>(), vec!["/users"]);
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
-use anyhow::Result;
+use crate::openapi::merge::{self, ConflictPolicy};
+use anyhow::{bail, Result};
 use clap::Args;
 use openapiv3::OpenAPI;
 use std::fs::File;
@@ -10,6 +11,10 @@ pub struct Merge {
 
     #[arg(short, long)]
     output: Option<String>,
+
+    /// How to resolve an operation or component that two specs define differently
+    #[arg(long, value_enum, default_value = "prefer-left")]
+    policy: ConflictPolicy,
 }
 
 impl Merge {
@@ -19,11 +24,19 @@ impl Merge {
         let mut spec = serde_yaml::from_reader::<_, OpenAPI>(File::open(&first)?)?;
 
         eprintln!("{}: Read file to spec.", first);
+        let mut failed = 0;
         for filepath in it {
             let update = serde_yaml::from_reader::<_, OpenAPI>(File::open(&filepath)?)?;
-            spec = spec.merge(update).map_err(|e| anyhow::anyhow!(e))?;
+            let conflicts = merge::merge_specs(&mut spec, update, self.policy);
+            for conflict in &conflicts {
+                eprintln!("{}: {}", filepath, conflict);
+            }
+            failed += conflicts.len();
             eprintln!("{}: Added file to spec.", filepath);
         }
+        if self.policy == ConflictPolicy::Fail && failed > 0 {
+            bail!("{} conflicts between the specs", failed);
+        }
         let output = serde_yaml::to_string(&spec).map_err(|e| anyhow::anyhow!(e))?;
         if let Some(path) = self.output {
             std::fs::write(&path, &output)?;
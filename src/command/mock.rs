@@ -0,0 +1,183 @@
+use crate::command::Generate;
+use crate::http::{read_har, Request, RequestResponse, Response};
+use crate::mock::Mock as MockServer;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use openapiv3::OpenAPI;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+use tracing::debug;
+use url::Url;
+
+#[derive(Debug, Args)]
+pub struct Mock {
+    /// HAR files whose responses are replayed
+    #[arg(num_args = 0..)]
+    har_files: Vec<String>,
+
+    /// Spec used to generate example responses for requests that match nothing recorded. Defaults to one
+    /// generated from the first HAR file.
+    #[arg(long)]
+    spec: Option<String>,
+
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+}
+
+/// A header with a constant ASCII name and value.
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Convert an incoming request into the model used for HAR entries.
+fn read_request(request: &mut tiny_http::Request) -> Result<Request> {
+    let url = Url::parse(&format!("http://localhost{}", request.url()))?;
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body)?;
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("content-type"))
+        .map(|h| h.value.to_string());
+    Ok(Request {
+        query: url.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        url,
+        headers: request.headers().iter().map(|h| (h.field.to_string(), h.value.to_string())).collect(),
+        method: request.method().to_string(),
+        body: (!body.is_empty()).then(|| crate::http::RequestBody {
+            mime: content_type.unwrap_or_default(),
+            content: serde_json::from_str(&body).unwrap_or(Value::String(body)),
+        }),
+        authorization: None,
+        cookies: Vec::new(),
+    })
+}
+
+/// Serve `response` with its recorded media type and cookies. The media type is guessed from the
+/// body when it is unknown.
+fn write_response(response: Response) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let (body, guessed) = match response.data {
+        Value::Null => (Vec::new(), None),
+        Value::String(text) => (text.into_bytes(), Some("text/plain; charset=utf-8")),
+        data => (data.to_string().into_bytes(), Some("application/json")),
+    };
+    let mut out = tiny_http::Response::from_data(body).with_status_code(response.status);
+    let content_type = guessed.map(|guessed| if response.mime.is_empty() { guessed } else { response.mime.as_str() });
+    if let Some(header) = content_type.and_then(|c| tiny_http::Header::from_bytes("Content-Type", c).ok()) {
+        out.add_header(header);
+    }
+    for cookie in &response.set_cookies {
+        if let Ok(header) = tiny_http::Header::from_bytes("Set-Cookie", cookie.as_bytes()) {
+            out.add_header(header);
+        }
+    }
+    // The frontend usually runs on another port, so allow any origin.
+    out.add_header(header("Access-Control-Allow-Origin", "*"));
+    for (name, value) in response.headers {
+        if name.eq_ignore_ascii_case("access-control-allow-origin") {
+            continue;
+        }
+        // Recorded headers may hold bytes tiny_http can't send; skip those rather than fail.
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            out.add_header(header);
+        }
+    }
+    out
+}
+
+impl Mock {
+    pub fn run(self) -> Result<()> {
+        let mut recorded = Vec::new();
+        for path in &self.har_files {
+            // Requests without an object name, e.g. `GET /`, are never looked up, so leave them out.
+            recorded.extend(read_har(path)?.into_iter().filter_map(RequestResponse::try_from_entry));
+        }
+        let spec = match (&self.spec, self.har_files.first()) {
+            (Some(path), _) => Some(serde_yaml::from_reader::<_, OpenAPI>(File::open(path)?)?),
+            (None, Some(har_file)) => Some(Generate::with_defaults(har_file)?.create_spec()?),
+            (None, None) => None,
+        };
+        anyhow::ensure!(!recorded.is_empty() || spec.is_some(), "Provide HAR files or a --spec to mock.");
+        let mock = MockServer::new(recorded, spec);
+
+        let addr = format!("{}:{}", self.host, self.port);
+        let server = tiny_http::Server::http(&addr).map_err(|e| anyhow!("{}: {}", addr, e))?;
+        eprintln!("Mock server listening on http://{}", addr);
+        for mut request in server.incoming_requests() {
+            let (method, url) = (request.method().to_string(), request.url().to_string());
+            if request.method() == &tiny_http::Method::Options {
+                let preflight = tiny_http::Response::empty(204)
+                    .with_header(header("Access-Control-Allow-Origin", "*"))
+                    .with_header(header("Access-Control-Allow-Methods", "GET, POST, PUT, PATCH, DELETE, OPTIONS"))
+                    .with_header(header("Access-Control-Allow-Headers", "*"));
+                if let Err(e) = request.respond(preflight) {
+                    eprintln!("{} {}: {}", method, url, e);
+                }
+                continue;
+            }
+            let response = match read_request(&mut request) {
+                Ok(req) => mock.respond(&req),
+                Err(e) => {
+                    debug!(error = %e, "Unreadable request");
+                    Response {
+                        status: 400,
+                        data: Value::String(e.to_string()),
+                        mime: "text/plain".to_string(),
+                        headers: Vec::new(),
+                        set_cookies: Vec::new(),
+                    }
+                }
+            };
+            eprintln!("{} {}: {}", method, url, response.status);
+            // A client that disconnects early shouldn't stop the server.
+            if let Err(e) = request.respond(write_response(response)) {
+                eprintln!("{} {}: {}", method, url, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_response_skips_unrepresentable_headers() {
+        let response = write_response(Response {
+            status: 200,
+            data: Value::Null,
+            mime: String::new(),
+            set_cookies: Vec::new(),
+            headers: vec![
+                ("X-Name".to_string(), "caf\u{e9}\n".to_string()),
+                ("X-Request-Id".to_string(), "abc".to_string()),
+            ],
+        });
+        let names = response.headers().iter().map(|h| h.field.to_string()).collect::<Vec<_>>();
+        assert!(names.contains(&"X-Request-Id".to_string()));
+        assert!(!names.contains(&"X-Name".to_string()));
+    }
+
+    #[test]
+    fn test_write_response_uses_recorded_mime_and_cookies() {
+        let response = write_response(Response {
+            status: 200,
+            data: Value::String("<html></html>".to_string()),
+            mime: "text/html; charset=utf-8".to_string(),
+            headers: Vec::new(),
+            set_cookies: vec!["sessionid=abc; HttpOnly".to_string(), "theme=dark".to_string()],
+        });
+        let headers = response
+            .headers()
+            .iter()
+            .map(|h| (h.field.to_string(), h.value.to_string()))
+            .collect::<Vec<_>>();
+        assert!(headers.contains(&("Content-Type".to_string(), "text/html; charset=utf-8".to_string())));
+        assert_eq!(headers.iter().filter(|(name, _)| name == "Set-Cookie").count(), 2);
+    }
+}
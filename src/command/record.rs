@@ -0,0 +1,199 @@
+use crate::command::Generate;
+use crate::http::Header;
+use crate::record::{write_har, Exchange};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use url::Url;
+
+/// Headers that belong to a single connection and aren't forwarded. `accept-encoding` is dropped
+/// too, since the upstream client only decodes gzip.
+fn hop_by_hop(name: &str) -> bool {
+    [
+        "host",
+        "connection",
+        "proxy-connection",
+        "keep-alive",
+        "proxy-authorization",
+        "te",
+        "trailer",
+        "transfer-encoding",
+        "upgrade",
+        "content-length",
+        "content-encoding",
+        "accept-encoding",
+    ]
+    .contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// How often new exchanges are saved while recording. Rewriting the file after every exchange gets slow
+/// once the recording is large.
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Args)]
+pub struct Record {
+    /// Forward every request to this server, e.g. `https://api.example.com`, and point the client at the proxy
+    /// instead. Without it, the proxy acts as a forward proxy for plain HTTP (`HTTP_PROXY=http://127.0.0.1:8888`).
+    #[arg(long)]
+    target: Option<String>,
+
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[arg(short, long, default_value_t = 8888)]
+    port: u16,
+
+    /// HAR file to write. It is rewritten every few seconds while recording, so little is lost if the proxy is killed.
+    #[arg(short, long, default_value = "recording.har")]
+    output: String,
+
+    /// On shutdown (Ctrl-C), also generate an OpenAPI spec from the recording into this file
+    #[arg(long)]
+    generate: Option<String>,
+}
+
+impl Record {
+    /// The upstream URL: the target's origin and path followed by the request's, or the absolute URL a
+    /// forward proxy receives.
+    fn upstream_url(&self, request_url: &str) -> Result<Url> {
+        match &self.target {
+            Some(target) => {
+                let mut url = Url::parse(target)?;
+                // Clients configured with the proxy as HTTP_PROXY send absolute URLs.
+                let request = Url::parse(request_url).or_else(|_| Url::parse(&format!("http://proxy{}", request_url)))?;
+                let path = format!("{}{}", url.path().trim_end_matches('/'), request.path());
+                url.set_path(&path);
+                url.set_query(request.query());
+                Ok(url)
+            }
+            None => Url::parse(request_url).map_err(|_| {
+                anyhow!("{} is not an absolute URL. Use the proxy as HTTP_PROXY, or pass --target.", request_url)
+            }),
+        }
+    }
+
+    fn forward(&self, agent: &ureq::Agent, request: &mut tiny_http::Request) -> Result<Exchange> {
+        let started = SystemTime::now();
+        let timer = Instant::now();
+        let url = self.upstream_url(request.url())?;
+        let method = request.method().to_string();
+        let request_headers = request
+            .headers()
+            .iter()
+            .map(|h| (h.field.to_string(), h.value.to_string()))
+            .filter(|(name, _)| !hop_by_hop(name))
+            .collect::<Vec<Header>>();
+        let mut request_body = Vec::new();
+        request.as_reader().read_to_end(&mut request_body)?;
+
+        let mut upstream = agent.request_url(&method, &url);
+        for (name, value) in &request_headers {
+            upstream = upstream.set(name, value);
+        }
+        let response = match upstream.send_bytes(&request_body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.into()),
+        };
+        let status = response.status();
+        let status_text = response.status_text().to_string();
+        let response_headers = response
+            .headers_names()
+            .into_iter()
+            .filter(|name| !hop_by_hop(name))
+            .flat_map(|name| response.all(&name).into_iter().map(|v| (name.clone(), v.to_string())).collect::<Vec<_>>())
+            .collect::<Vec<Header>>();
+        let mut response_body = Vec::new();
+        response.into_reader().read_to_end(&mut response_body)?;
+        Ok(Exchange {
+            started,
+            elapsed: timer.elapsed(),
+            method,
+            url: url.to_string(),
+            request_headers,
+            request_body,
+            status,
+            status_text,
+            response_headers,
+            response_body,
+        })
+    }
+
+    pub fn run(self) -> Result<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handler_stop = stop.clone();
+        ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))?;
+        // Redirects are passed on to the client, which records them as separate exchanges.
+        let agent = ureq::AgentBuilder::new().redirects(0).build();
+
+        let addr = format!("{}:{}", self.host, self.port);
+        let server = tiny_http::Server::http(&addr).map_err(|e| anyhow!("{}: {}", addr, e))?;
+        match &self.target {
+            Some(target) => eprintln!("Recording requests to http://{} for {}. Press Ctrl-C to stop.", addr, target),
+            None => eprintln!("Recording requests through proxy http://{}. Press Ctrl-C to stop.", addr),
+        }
+        let mut entries = Vec::new();
+        let (mut saved, mut last_save) = (0, Instant::now());
+        while !stop.load(Ordering::SeqCst) {
+            if entries.len() > saved && last_save.elapsed() >= SAVE_INTERVAL {
+                // Keep recording if the file can't be written; it's retried on the next save.
+                match write_har(&self.output, &entries) {
+                    Ok(()) => saved = entries.len(),
+                    Err(e) => eprintln!("{}: {}", self.output, e),
+                }
+                last_save = Instant::now();
+            }
+            let Some(mut request) = server.recv_timeout(Duration::from_millis(200))? else {
+                continue;
+            };
+            let label = format!("{} {}", request.method(), request.url());
+            if request.method() == &tiny_http::Method::Connect {
+                let message = "HTTPS can't be recorded through the forward proxy. Use --target instead.";
+                if let Err(e) = request.respond(tiny_http::Response::from_string(message).with_status_code(501)) {
+                    eprintln!("{}: {}", label, e);
+                }
+                continue;
+            }
+            let exchange = match self.forward(&agent, &mut request) {
+                Ok(exchange) => exchange,
+                Err(e) => {
+                    eprintln!("{}: {}", label, e);
+                    let response = tiny_http::Response::from_string(e.to_string()).with_status_code(502);
+                    if let Err(e) = request.respond(response) {
+                        eprintln!("{}: {}", label, e);
+                    }
+                    continue;
+                }
+            };
+            eprintln!("{} {}: {}", exchange.method, exchange.url, exchange.status);
+            let mut response = tiny_http::Response::from_data(exchange.response_body.clone()).with_status_code(exchange.status);
+            for (name, value) in &exchange.response_headers {
+                if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                    response.add_header(header);
+                }
+            }
+            let url = exchange.url.clone();
+            match exchange.into_entry() {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("{}: not recorded, {}", url, e),
+            }
+            if let Err(e) = request.respond(response) {
+                eprintln!("{}: {}", label, e);
+            }
+        }
+        if entries.is_empty() {
+            eprintln!("No requests recorded.");
+            return Ok(());
+        }
+        write_har(&self.output, &entries)?;
+        eprintln!("{}: Wrote file.", self.output);
+        if let Some(spec) = self.generate {
+            let mut generate = Generate::with_defaults(&self.output)?;
+            generate.output = Some(spec);
+            generate.run()?;
+        }
+        Ok(())
+    }
+}
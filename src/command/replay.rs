@@ -0,0 +1,125 @@
+use crate::http::{read_har, Request, RequestResponse, Response};
+use crate::replay::Comparer;
+use anyhow::{bail, Result};
+use clap::Args;
+use serde_json::Value;
+use url::Url;
+
+#[derive(Debug, Args)]
+pub struct Replay {
+    har_file: String,
+
+    /// Where to send the requests, e.g. `http://localhost:3000`. The recorded path and query are appended.
+    #[arg(long)]
+    base_url: String,
+
+    /// Only replay GET and HEAD requests, so nothing on the target changes
+    #[arg(long)]
+    read_only: bool,
+
+    /// Extra header to send with every request, replacing a recorded one with the same name, e.g.
+    /// `-H "Authorization: Bearer abc"` for a fresh token. Can be repeated.
+    #[arg(short = 'H', long = "header")]
+    headers: Vec<String>,
+
+    /// Leave this response property out of the comparison (case-insensitive), in addition to built-in volatile
+    /// ones like `updated_at` and `request_id`. Can be repeated.
+    #[arg(long)]
+    ignore: Vec<String>,
+}
+
+impl Replay {
+    /// The URL of `request` on the target: the base URL's origin and path, followed by the recorded
+    /// path and query.
+    fn target_url(&self, request: &Request) -> Result<Url> {
+        let mut url = Url::parse(&self.base_url)?;
+        let path = format!("{}{}", url.path().trim_end_matches('/'), request.url.path());
+        url.set_path(&path);
+        url.set_query(request.url.query());
+        Ok(url)
+    }
+
+    fn send(&self, request: &Request) -> Result<Response> {
+        let mut req = ureq::request(&request.method, self.target_url(request)?.as_str());
+        let extra = self
+            .headers
+            .iter()
+            .filter_map(|h| h.split_once(':'))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .collect::<Vec<_>>();
+        let overridden = |name: &str| extra.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+        for (name, value) in request.headers.iter().filter(|(n, _)| !overridden(n)) {
+            req = req.set(name, value);
+        }
+        if let Some(authorization) = request.authorization.as_deref().filter(|_| !overridden("authorization")) {
+            req = req.set("Authorization", authorization);
+        }
+        if !request.cookies.is_empty() && !overridden("cookie") {
+            let cookie = request.cookies.iter().map(|(n, v)| format!("{}={}", n, v)).collect::<Vec<_>>().join("; ");
+            req = req.set("Cookie", &cookie);
+        }
+        for (name, value) in &extra {
+            req = req.set(name, value);
+        }
+        let result = match &request.body {
+            Some(body) => {
+                let text = match &body.content {
+                    Value::String(text) => text.clone(),
+                    content => content.to_string(),
+                };
+                req.set("Content-Type", &body.mime).send_string(&text)
+            }
+            None => req.call(),
+        };
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.into()),
+        };
+        let status = response.status();
+        let mime = response.content_type().to_string();
+        let text = response.into_string()?;
+        Ok(Response {
+            status,
+            data: if text.is_empty() { Value::Null } else { serde_json::from_str(&text).unwrap_or(Value::String(text)) },
+            mime,
+            headers: Vec::new(),
+            set_cookies: Vec::new(),
+        })
+    }
+
+    pub fn run(self) -> Result<()> {
+        let comparer = Comparer::new(&self.ignore);
+        let mut regressions = 0;
+        let mut replayed = 0;
+        for (i, entry) in read_har(&self.har_file)?.into_iter().enumerate() {
+            let url = entry.request.url.clone();
+            let Some(rr) = RequestResponse::try_from_entry(entry) else {
+                eprintln!("entry {} ({}): skipped, no object name in the path", i, url);
+                continue;
+            };
+            if self.read_only && !["GET", "HEAD"].contains(&rr.method().to_uppercase().as_str()) {
+                continue;
+            }
+            let name = format!("entry {} ({} {})", i, rr.method().to_uppercase(), rr.request.url.path());
+            replayed += 1;
+            let actual = match self.send(&rr.request) {
+                Ok(actual) => actual,
+                Err(e) => {
+                    println!("{}: request failed: {}", name, e);
+                    regressions += 1;
+                    continue;
+                }
+            };
+            for difference in comparer.compare(&rr.response, &actual) {
+                let label = if difference.regression { "regression" } else { "change" };
+                println!("{}: {}: {}: {}", name, label, difference.pointer, difference.message);
+                regressions += difference.regression as usize;
+            }
+        }
+        if regressions > 0 {
+            bail!("{}: {} regressions in {} replayed requests", self.har_file, regressions, replayed);
+        }
+        eprintln!("{}: {} replayed requests match.", self.har_file, replayed);
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::http::read_har;
+use crate::openapi::validate;
+use anyhow::{bail, Result};
+use clap::Args;
+use openapiv3::OpenAPI;
+use std::fs::File;
+
+#[derive(Debug, Args)]
+pub struct Validate {
+    /// The OpenAPI 3.0 spec to check against
+    spec: String,
+
+    har_file: String,
+
+    /// Print the violations as JSON instead of one per line
+    #[arg(long)]
+    json: bool,
+}
+
+impl Validate {
+    pub fn run(self) -> Result<()> {
+        let spec: OpenAPI = serde_yaml::from_reader(File::open(&self.spec)?)?;
+        let violations = read_har(&self.har_file)?
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, entry)| validate::validate_har_entry(&spec, i, entry))
+            .collect::<Vec<_>>();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&violations)?);
+        } else {
+            for violation in &violations {
+                println!("{}", violation);
+            }
+        }
+        if !violations.is_empty() {
+            bail!("{}: {} violations of {}", self.har_file, violations.len(), self.spec);
+        }
+        eprintln!("{}: All entries match {}.", self.har_file, self.spec);
+        Ok(())
+    }
+}
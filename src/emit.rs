@@ -0,0 +1,66 @@
+pub mod rust;
+pub mod typescript;
+
+use convert_case::{Case, Casing};
+use indexmap::IndexMap;
+use openapiv3 as oa;
+
+/// A type name for a schema or property, e.g. `line_items` -> `LineItems`. Characters that
+/// can't appear in identifiers are dropped, and a leading digit gets a `T` prefix.
+pub fn type_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_case(Case::Pascal);
+    match name.chars().next() {
+        None => "Type".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("T{}", name),
+        Some(_) => name,
+    }
+}
+
+/// The schema name a reference points to, e.g. `User` for `#/components/schemas/User`.
+pub fn ref_name(reference: &str) -> &str {
+    reference.rsplit('/').next().unwrap_or(reference)
+}
+
+/// Make `name` unique among `taken` by appending a number, and reserve it.
+pub fn unique_name(name: String, taken: &mut Vec<String>) -> String {
+    let mut candidate = name.clone();
+    let mut i = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}{}", name, i);
+        i += 1;
+    }
+    taken.push(candidate.clone());
+    candidate
+}
+
+/// The identifier of each component schema, made unique after [type_name] so that e.g. `user`
+/// and `User` become `User` and `User2`. Both declarations and references use it.
+pub struct ComponentNames(IndexMap<String, String>);
+
+impl ComponentNames {
+    /// Name the components of `spec`, avoiding the `reserved` names the generated code uses itself,
+    /// e.g. `Option` in Rust.
+    pub fn new(spec: &oa::OpenAPI, reserved: &[&str]) -> Self {
+        let mut taken = reserved.iter().map(|r| r.to_string()).collect();
+        let names = spec.components.schemas.keys().map(|n| (n.clone(), unique_name(type_name(n), &mut taken)));
+        ComponentNames(names.collect())
+    }
+
+    /// The identifier of the component `name`.
+    pub fn get(&self, name: &str) -> String {
+        self.0.get(name).cloned().unwrap_or_else(|| type_name(name))
+    }
+
+    /// The identifier of the component a reference points to.
+    pub fn reference(&self, reference: &str) -> String {
+        self.get(ref_name(reference))
+    }
+
+    pub fn identifiers(&self) -> Vec<String> {
+        self.0.values().cloned().collect()
+    }
+}
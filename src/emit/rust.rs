@@ -0,0 +1,425 @@
+use crate::emit::{ref_name, type_name, unique_name, ComponentNames};
+use crate::openapi::schema::resolve_schema;
+use convert_case::{Case, Casing};
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use tracing::warn;
+
+const DERIVE: &str = "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]";
+
+const KEYWORDS: [&str; 47] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// A snake_case field name for a JSON key, e.g. `createdAt` -> `created_at` and `type` -> `r#type`.
+fn field_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .to_case(Case::Snake);
+    let name = match name.chars().next() {
+        None => "field".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{}", name),
+        Some(_) => name,
+    };
+    match name.as_str() {
+        // These can't be raw identifiers.
+        "self" | "super" | "crate" => format!("{}_", name),
+        n if KEYWORDS.contains(&n) => format!("r#{}", n),
+        _ => name,
+    }
+}
+
+fn doc(out: &mut String, indent: &str, description: Option<&str>) {
+    for line in description.iter().flat_map(|d| d.lines()) {
+        writeln!(out, "{}/// {}", indent, line).unwrap();
+    }
+}
+
+/// Add a `#[serde(rename = "...")]` when the Rust name doesn't serialize as `key`.
+fn rename(out: &mut String, indent: &str, ident: &str, key: &str) {
+    if ident.trim_start_matches("r#") != key {
+        writeln!(out, "{}#[serde(rename = {:?})]", indent, key).unwrap();
+    }
+}
+
+/// The components a schema holds by value, i.e. not behind a `Vec` or map. A cycle of these
+/// needs a `Box` to have a finite size.
+fn direct_refs<'a>(schema: &'a RefOr<oa::Schema>, refs: &mut Vec<&'a str>) {
+    use oa::SchemaKind::Type as T;
+    let schema = match schema {
+        RefOr::Reference { reference } => return refs.push(ref_name(reference)),
+        RefOr::Item(schema) => schema,
+    };
+    match &schema.kind {
+        T(oa::Type::Object(o)) => o.properties.values().for_each(|p| direct_refs(p, refs)),
+        oa::SchemaKind::AllOf { all_of: parts } | oa::SchemaKind::OneOf { one_of: parts } | oa::SchemaKind::AnyOf { any_of: parts } => {
+            parts.iter().for_each(|p| direct_refs(p, refs))
+        }
+        _ => {}
+    }
+}
+
+/// Types the generated code refers to by their bare name, which components mustn't shadow.
+const RESERVED: &[&str] = &["Option", "Box", "Vec", "String", "Serialize", "Deserialize", "Self"];
+
+struct RustEmitter<'a> {
+    spec: &'a oa::OpenAPI,
+    names: ComponentNames,
+    /// The component being emitted, including the types nested in it.
+    component: String,
+    items: Vec<String>,
+    taken: Vec<String>,
+    /// The tag property of each variant struct of an internally tagged enum. serde consumes
+    /// the tag, so the struct must not declare it.
+    tags: HashMap<String, String>,
+}
+
+/// Generate serde structs and enums for the components of `spec`: objects become structs,
+/// string enums and `oneOf`s become enums, and everything else a type alias.
+pub fn emit_rust(spec: &oa::OpenAPI) -> String {
+    let names = ComponentNames::new(spec, RESERVED);
+    let mut emitter = RustEmitter {
+        spec,
+        taken: names.identifiers(),
+        names,
+        component: String::new(),
+        items: Vec::new(),
+        tags: HashMap::new(),
+    };
+    for (_, schema) in spec.components.schemas.iter() {
+        let Some(schema) = schema.as_item() else {
+            continue;
+        };
+        if let (oa::SchemaKind::OneOf { one_of }, Some(discriminator)) = (&schema.kind, &schema.data.discriminator) {
+            for reference in one_of.iter().filter_map(|v| v.as_ref_str()) {
+                emitter.tags.insert(emitter.names.reference(reference), discriminator.property_name.clone());
+            }
+        }
+    }
+    for (component, schema) in spec.components.schemas.iter() {
+        let name = emitter.names.get(component);
+        emitter.component = component.clone();
+        match schema {
+            RefOr::Item(schema) => emitter.named(&name, schema),
+            RefOr::Reference { .. } => {
+                let item = format!("pub type {} = {};\n", name, emitter.type_expr(schema, &name));
+                emitter.items.push(item)
+            }
+        }
+    }
+    let mut out = String::from("//! Generated by har2oa. Do not edit by hand.\n\nuse serde::{Deserialize, Serialize};\n");
+    for item in emitter.items {
+        out.push('\n');
+        out.push_str(&item);
+    }
+    out
+}
+
+impl RustEmitter<'_> {
+    /// Emit the type `name` for `schema`. Types nested in it are emitted after it.
+    fn named(&mut self, name: &str, schema: &oa::Schema) {
+        use oa::SchemaKind::Type as T;
+        let slot = self.items.len();
+        self.items.push(String::new());
+        let mut out = String::new();
+        doc(&mut out, "", schema.data.description.as_deref());
+        match &schema.kind {
+            T(oa::Type::String(s)) if !s.enumeration.is_empty() => {
+                writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]").unwrap();
+                writeln!(out, "pub enum {} {{", name).unwrap();
+                let mut variants = Vec::new();
+                for value in &s.enumeration {
+                    let ident = if value.is_empty() { "Empty".to_string() } else { type_name(value) };
+                    let ident = unique_name(ident, &mut variants);
+                    rename(&mut out, "    ", &ident, value);
+                    writeln!(out, "    {},", ident).unwrap();
+                }
+                writeln!(out, "}}").unwrap();
+            }
+            T(oa::Type::Object(o)) if !o.properties.is_empty() => {
+                let properties = o.properties.iter().map(|(k, v)| (k.as_str(), v)).collect::<Vec<_>>();
+                self.structure(&mut out, name, &[], &properties, &o.required);
+            }
+            oa::SchemaKind::AllOf { all_of } => {
+                let mut flattened = Vec::new();
+                let mut properties = Vec::new();
+                let mut required = Vec::new();
+                for (i, part) in all_of.iter().enumerate() {
+                    match part {
+                        RefOr::Item(oa::Schema { kind: T(oa::Type::Object(o)), .. }) => {
+                            properties.extend(o.properties.iter().map(|(k, v)| (k.as_str(), v)));
+                            required.extend(o.required.iter().cloned());
+                        }
+                        part => flattened.push(self.type_expr(part, &format!("{}Part{}", name, i + 1))),
+                    }
+                }
+                self.structure(&mut out, name, &flattened, &properties, &required);
+            }
+            oa::SchemaKind::OneOf { one_of: variants } | oa::SchemaKind::AnyOf { any_of: variants } => {
+                writeln!(out, "{}", DERIVE).unwrap();
+                match &schema.data.discriminator {
+                    Some(discriminator) => {
+                        writeln!(out, "#[serde(tag = {:?})]", discriminator.property_name).unwrap();
+                        writeln!(out, "pub enum {} {{", name).unwrap();
+                        let tagged = match discriminator.mapping.is_empty() {
+                            false => discriminator.mapping.iter().map(|(v, r)| (v.clone(), r.as_str())).collect::<Vec<_>>(),
+                            true => variants.iter().filter_map(|v| v.as_ref_str()).map(|r| (ref_name(r).to_string(), r)).collect(),
+                        };
+                        let mut idents = Vec::new();
+                        for (value, reference) in tagged {
+                            let ident = unique_name(type_name(&value), &mut idents);
+                            rename(&mut out, "    ", &ident, &value);
+                            writeln!(out, "    {}({}),", ident, self.names.reference(reference)).unwrap();
+                        }
+                    }
+                    None => {
+                        writeln!(out, "#[serde(untagged)]").unwrap();
+                        writeln!(out, "pub enum {} {{", name).unwrap();
+                        let mut idents = Vec::new();
+                        for (i, variant) in variants.iter().enumerate() {
+                            let payload = self.type_expr(variant, &format!("{}Variant{}", name, i + 1));
+                            let ident = match payload.chars().all(|c| c.is_ascii_alphanumeric()) {
+                                true => type_name(&payload),
+                                false => format!("Variant{}", i + 1),
+                            };
+                            let ident = unique_name(ident, &mut idents);
+                            writeln!(out, "    {}({}),", ident, payload).unwrap();
+                        }
+                    }
+                }
+                writeln!(out, "}}").unwrap();
+            }
+            _ => {
+                let ty = self.schema_type(schema, name);
+                writeln!(out, "pub type {} = {};", name, ty).unwrap();
+            }
+        }
+        self.items[slot] = out;
+    }
+
+    fn structure(
+        &mut self,
+        out: &mut String,
+        name: &str,
+        flattened: &[String],
+        properties: &[(&str, &RefOr<oa::Schema>)],
+        required: &[String],
+    ) {
+        writeln!(out, "{}", DERIVE).unwrap();
+        writeln!(out, "pub struct {} {{", name).unwrap();
+        let mut fields = Vec::new();
+        for ty in flattened {
+            writeln!(out, "    #[serde(flatten)]").unwrap();
+            writeln!(out, "    pub {}: {},", unique_name(field_name(ty), &mut fields), ty).unwrap();
+        }
+        let tag = self.tags.get(name).cloned();
+        for (key, property) in properties {
+            if tag.as_deref() == Some(*key) {
+                continue;
+            }
+            let ident = unique_name(field_name(key), &mut fields);
+            let mut ty = self.type_expr(property, &format!("{}{}", name, type_name(key)));
+            if let RefOr::Reference { reference } = property {
+                if self.reaches(ref_name(reference), &self.component) {
+                    ty = format!("Box<{}>", ty);
+                }
+            }
+            doc(out, "    ", property.as_item().and_then(|p| p.data.description.as_deref()));
+            rename(out, "    ", &ident, key);
+            if !required.iter().any(|r| r == key) {
+                writeln!(out, "    #[serde(default, skip_serializing_if = \"Option::is_none\")]").unwrap();
+                ty = format!("Option<{}>", ty);
+            } else if resolve_schema(self.spec, property).is_some_and(|p| p.data.nullable) {
+                ty = format!("Option<{}>", ty);
+            }
+            writeln!(out, "    pub {}: {},", ident, ty).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    /// Whether the component `from` holds `to` by value, directly or through other components.
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(name) = stack.pop() {
+            if name == to {
+                return true;
+            }
+            if seen.insert(name) {
+                if let Some(schema) = self.spec.components.schemas.get(name) {
+                    direct_refs(schema, &mut stack);
+                }
+            }
+        }
+        false
+    }
+
+    /// The Rust type for a property, array item or variant. `context` names any type that has to
+    /// be emitted for it, e.g. `UserAddress` for an inline object in `User.address`. A reference
+    /// to a missing schema becomes `serde_json::Value`.
+    fn type_expr(&mut self, schema: &RefOr<oa::Schema>, context: &str) -> String {
+        match schema {
+            RefOr::Reference { reference } if resolve_schema(self.spec, schema).is_none() => {
+                warn!(reference, "Missing schema reference");
+                "serde_json::Value".to_string()
+            }
+            RefOr::Reference { reference } => self.names.reference(reference),
+            RefOr::Item(schema) => self.schema_type(schema, context),
+        }
+    }
+
+    fn schema_type(&mut self, schema: &oa::Schema, context: &str) -> String {
+        use oa::SchemaKind::Type as T;
+        use oa::VariantOrUnknownOrEmpty::Item;
+        match &schema.kind {
+            T(oa::Type::String(s)) if s.enumeration.is_empty() => match &s.format {
+                Item(oa::StringFormat::Date) => "chrono::NaiveDate".to_string(),
+                Item(oa::StringFormat::DateTime) => "chrono::DateTime<chrono::Utc>".to_string(),
+                _ => "String".to_string(),
+            },
+            T(oa::Type::Integer(i)) => match i.format {
+                Item(oa::IntegerFormat::Int32) => "i32".to_string(),
+                _ => "i64".to_string(),
+            },
+            T(oa::Type::Number(_)) => "f64".to_string(),
+            T(oa::Type::Boolean {}) => "bool".to_string(),
+            T(oa::Type::Array(a)) => match &a.items {
+                Some(items) => format!("Vec<{}>", self.type_expr(items, &format!("{}Item", context))),
+                None => "Vec<serde_json::Value>".to_string(),
+            },
+            T(oa::Type::Object(o)) if o.properties.is_empty() => match &o.additional_properties {
+                Some(oa::AdditionalProperties::Schema(values)) => {
+                    let values = self.type_expr(values, &format!("{}Value", context));
+                    format!("std::collections::HashMap<String, {}>", values)
+                }
+                _ => "serde_json::Value".to_string(),
+            },
+            T(_) | oa::SchemaKind::OneOf { .. } | oa::SchemaKind::AnyOf { .. } | oa::SchemaKind::AllOf { .. } => {
+                let name = unique_name(context.to_string(), &mut self.taken);
+                self.named(&name, schema);
+                name
+            }
+            oa::SchemaKind::Not { .. } | oa::SchemaKind::Any(_) => "serde_json::Value".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_emit_rust() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "User": {"type": "object", "required": ["id", "type"], "properties": {
+                    "id": {"type": "integer"},
+                    "type": {"type": "string", "enum": ["admin", "member"]},
+                    "createdAt": {"type": "string", "format": "date-time"},
+                    "manager": {"$ref": "#/components/schemas/User"},
+                }},
+                "Pet": {
+                    "oneOf": [{"$ref": "#/components/schemas/CatPet"}],
+                    "discriminator": {"propertyName": "kind", "mapping": {"cat": "#/components/schemas/CatPet"}},
+                },
+                "CatPet": {"type": "object", "required": ["kind", "lives"], "properties": {
+                    "kind": {"type": "string", "enum": ["cat"]},
+                    "lives": {"type": "integer", "format": "int32", "nullable": true},
+                }},
+            }},
+        }))
+        .unwrap();
+        let code = emit_rust(&spec);
+        assert!(code.contains(r#"pub struct User {
+    pub id: i64,
+    pub r#type: UserType,
+    #[serde(rename = "createdAt")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manager: Option<Box<User>>,
+}"#), "{}", code);
+        assert!(code.contains("pub enum UserType {\n    #[serde(rename = \"admin\")]\n    Admin,"), "{}", code);
+        assert!(code.contains("#[serde(tag = \"kind\")]\npub enum Pet {\n    #[serde(rename = \"cat\")]\n    Cat(CatPet),\n}"), "{}", code);
+        assert!(code.contains("pub struct CatPet {\n    pub lives: Option<i32>,\n}"), "{}", code);
+    }
+
+    #[test]
+    fn test_emit_rust_collisions() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "user": {"type": "object", "required": ["createdAt", "created_at"], "properties": {
+                    "createdAt": {"type": "string"},
+                    "created_at": {"type": "string"},
+                }},
+                "User": {"type": "object", "required": ["team"], "properties": {
+                    "team": {"$ref": "#/components/schemas/Team"},
+                    "profile": {"$ref": "#/components/schemas/user"},
+                }},
+                "Team": {"type": "object", "required": ["owner"], "properties": {
+                    "owner": {"$ref": "#/components/schemas/User"},
+                    "members": {"type": "array", "items": {"$ref": "#/components/schemas/User"}},
+                }},
+            }},
+        }))
+        .unwrap();
+        let code = emit_rust(&spec);
+        assert!(code.contains("pub struct User {\n    #[serde(rename = \"createdAt\")]\n    pub created_at: String,\n    #[serde(rename = \"created_at\")]\n    pub created_at2: String,\n}"), "{}", code);
+        assert!(code.contains("pub struct User2 {\n    pub team: Box<Team>,\n    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub profile: Option<User>,\n}"), "{}", code);
+        assert!(code.contains("pub owner: Box<User2>,\n    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub members: Option<Vec<User2>>,"), "{}", code);
+    }
+
+    #[test]
+    fn test_emit_rust_reserved_names() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "Option": {"type": "object", "required": ["label"], "properties": {"label": {"type": "string"}}},
+                "Menu": {"type": "object", "properties": {
+                    "options": {"type": "array", "items": {"$ref": "#/components/schemas/Option"}},
+                }},
+            }},
+        }))
+        .unwrap();
+        let code = emit_rust(&spec);
+        assert!(code.contains("pub struct Option2 {\n    pub label: String,\n}"), "{}", code);
+        assert!(code.contains("pub options: Option<Vec<Option2>>,"), "{}", code);
+    }
+
+    #[test]
+    fn test_emit_rust_dangling_reference() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "User": {"type": "object", "required": ["team"], "properties": {
+                    "team": {"$ref": "#/components/schemas/Team"},
+                    "roles": {"type": "array", "items": {"$ref": "#/components/schemas/Role"}},
+                }},
+                "Member": {"$ref": "#/components/schemas/Person"},
+            }},
+        }))
+        .unwrap();
+        let code = emit_rust(&spec);
+        assert!(code.contains("pub team: serde_json::Value,"), "{}", code);
+        assert!(code.contains("pub roles: Option<Vec<serde_json::Value>>,"), "{}", code);
+        assert!(code.contains("pub type Member = serde_json::Value;"), "{}", code);
+    }
+}
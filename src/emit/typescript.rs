@@ -0,0 +1,243 @@
+use crate::emit::{type_name, unique_name, ComponentNames};
+use crate::openapi::validate::json_schema;
+use once_cell::sync::Lazy;
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use regex::Regex;
+use std::fmt::Write;
+
+/// Quote property names that aren't identifiers, e.g. `street-name`.
+fn property_key(key: &str) -> String {
+    static IDENTIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap());
+    if IDENTIFIER.is_match(key) {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap()
+    }
+}
+
+fn doc(out: &mut String, indent: &str, description: Option<&str>) {
+    if let Some(description) = description {
+        writeln!(out, "{}/** {} */", indent, description.replace("*/", "* /").replace('\n', " ")).unwrap();
+    }
+}
+
+fn properties(out: &mut String, names: &ComponentNames, object: &oa::ObjectType, indent: usize) {
+    let pad = "  ".repeat(indent + 1);
+    for (key, property) in object.properties.iter() {
+        doc(out, &pad, property.as_item().and_then(|p| p.data.description.as_deref()));
+        let optional = if object.required.contains(key) { "" } else { "?" };
+        writeln!(out, "{}{}{}: {};", pad, property_key(key), optional, type_expr(names, property, indent + 1)).unwrap();
+    }
+}
+
+/// The TypeScript type for a schema. Inline objects are written as object literals indented
+/// `indent` levels deep.
+fn type_expr(names: &ComponentNames, schema: &RefOr<oa::Schema>, indent: usize) -> String {
+    use oa::SchemaKind::Type as T;
+    let schema = match schema {
+        RefOr::Reference { reference } => return names.reference(reference),
+        RefOr::Item(schema) => schema,
+    };
+    let join = |schemas: &[RefOr<oa::Schema>], separator: &str| {
+        schemas.iter().map(|s| type_expr(names, s, indent)).collect::<Vec<_>>().join(separator)
+    };
+    let ty = match &schema.kind {
+        T(oa::Type::String(s)) if !s.enumeration.is_empty() => s
+            .enumeration
+            .iter()
+            .map(|v| serde_json::to_string(v).unwrap())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        T(oa::Type::String(_)) => "string".to_string(),
+        T(oa::Type::Integer(_)) | T(oa::Type::Number(_)) => "number".to_string(),
+        T(oa::Type::Boolean {}) => "boolean".to_string(),
+        T(oa::Type::Array(a)) => {
+            let items = a.items.as_ref().map(|i| type_expr(names, i, indent)).unwrap_or_else(|| "unknown".to_string());
+            if items.contains(" | ") || items.contains(" & ") {
+                format!("({})[]", items)
+            } else {
+                format!("{}[]", items)
+            }
+        }
+        T(oa::Type::Object(o)) if !o.properties.is_empty() => {
+            let mut out = String::from("{\n");
+            properties(&mut out, names, o, indent);
+            out.push_str(&"  ".repeat(indent));
+            out.push('}');
+            out
+        }
+        T(oa::Type::Object(o)) => match &o.additional_properties {
+            Some(oa::AdditionalProperties::Schema(values)) => format!("Record<string, {}>", type_expr(names, values, indent)),
+            // Only `null` was observed for this value.
+            _ if schema.data.nullable => "unknown".to_string(),
+            _ => "Record<string, unknown>".to_string(),
+        },
+        oa::SchemaKind::OneOf { one_of: schemas } | oa::SchemaKind::AnyOf { any_of: schemas } => join(schemas, " | "),
+        oa::SchemaKind::AllOf { all_of } => join(all_of, " & "),
+        oa::SchemaKind::Not { .. } | oa::SchemaKind::Any(_) => "unknown".to_string(),
+    };
+    if schema.data.nullable && ty != "unknown" {
+        format!("{} | null", ty)
+    } else {
+        ty
+    }
+}
+
+/// Declare a component: objects (and `allOf`s of references and objects) as interfaces,
+/// everything else, including enums and `oneOf`s, as a type alias.
+fn declaration(out: &mut String, names: &ComponentNames, name: &str, schema: &oa::Schema) {
+    use oa::SchemaKind::Type as T;
+    doc(out, "", schema.data.description.as_deref());
+    match &schema.kind {
+        T(oa::Type::Object(o)) if !o.properties.is_empty() && !schema.data.nullable => {
+            writeln!(out, "export interface {} {{", name).unwrap();
+            properties(out, names, o, 0);
+            writeln!(out, "}}").unwrap();
+        }
+        oa::SchemaKind::AllOf { all_of }
+            if all_of.iter().all(|s| s.as_ref_str().is_some() || matches!(s, RefOr::Item(oa::Schema { kind: T(oa::Type::Object(_)), .. }))) =>
+        {
+            let bases = all_of.iter().filter_map(|s| s.as_ref_str()).map(|r| names.reference(r)).collect::<Vec<_>>();
+            let extends = if bases.is_empty() { String::new() } else { format!(" extends {}", bases.join(", ")) };
+            writeln!(out, "export interface {}{} {{", name, extends).unwrap();
+            for part in all_of {
+                if let RefOr::Item(oa::Schema { kind: T(oa::Type::Object(o)), .. }) = part {
+                    properties(out, names, o, 0);
+                }
+            }
+            writeln!(out, "}}").unwrap();
+        }
+        _ => {
+            writeln!(out, "export type {} = {};", name, type_expr(names, &RefOr::Item(schema.clone()), 0)).unwrap();
+        }
+    }
+}
+
+/// Generate TypeScript declarations for the components of `spec`, plus `<OperationId>Request`
+/// and `<OperationId>Response` aliases for each operation's JSON request body and success
+/// response.
+pub fn emit_typescript(spec: &oa::OpenAPI) -> String {
+    let mut out = String::from("// Generated by har2oa. Do not edit by hand.\n");
+    // `Record` is used for maps, so a component must not shadow it.
+    let names = ComponentNames::new(spec, &["Record"]);
+    let mut taken = names.identifiers();
+    for (name, schema) in spec.components.schemas.iter() {
+        let name = names.get(name);
+        out.push('\n');
+        match schema {
+            RefOr::Reference { reference } => writeln!(out, "export type {} = {};", name, names.reference(reference)).unwrap(),
+            RefOr::Item(schema) => declaration(&mut out, &names, &name, schema),
+        }
+    }
+
+    let mut aliases = String::new();
+    for (path, item) in spec.paths.paths.iter().filter_map(|(path, item)| Some((path, item.as_item()?))) {
+        for (method, op) in item.iter() {
+            let base = type_name(op.operation_id.as_deref().unwrap_or(&format!("{} {}", method, path)));
+            let request = op
+                .request_body
+                .as_ref()
+                .and_then(|b| b.resolve(spec).ok())
+                .and_then(|b| json_schema(&b.content, None));
+            let response = op
+                .responses
+                .responses
+                .iter()
+                .find(|(status, _)| matches!(status, oa::StatusCode::Code(200..=299) | oa::StatusCode::Range(2)))
+                .and_then(|(_, r)| r.resolve(spec).ok())
+                .map(|r| json_schema(&r.content, None).map(|s| type_expr(&names, s, 0)).unwrap_or_else(|| "void".to_string()));
+            let types = [("Request", request.map(|s| type_expr(&names, s, 0))), ("Response", response)];
+            for (suffix, ty) in types {
+                let Some(ty) = ty else {
+                    continue;
+                };
+                let name = format!("{}{}", base, suffix);
+                // Response components are usually named after the operation already.
+                if ty == name {
+                    continue;
+                }
+                let name = unique_name(name, &mut taken);
+                writeln!(aliases, "export type {} = {};", name, ty).unwrap();
+            }
+        }
+    }
+    if !aliases.is_empty() {
+        out.push('\n');
+        out.push_str(&aliases);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_emit_typescript() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {
+                "/users": {"post": {
+                    "operationId": "createUser",
+                    "requestBody": {"content": {"application/json": {"schema": {
+                        "type": "object", "required": ["name"], "properties": {"name": {"type": "string"}},
+                    }}}},
+                    "responses": {"201": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}}},
+                }},
+                "/pets": {"get": {
+                    "operationId": "getPets",
+                    "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/GetPetsResponse"}}}}},
+                }},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "required": ["id"], "properties": {
+                    "id": {"type": "integer"},
+                    "status": {"type": "string", "enum": ["active", "archived"]},
+                    "street-name": {"type": "string", "nullable": true},
+                    "tags": {"type": "array", "items": {"type": "object", "properties": {"name": {"type": "string"}}}},
+                }},
+                "Pet": {"oneOf": [{"$ref": "#/components/schemas/Cat"}, {"$ref": "#/components/schemas/Dog"}]},
+                "GetPetsResponse": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}},
+            }},
+        }))
+        .unwrap();
+        let code = emit_typescript(&spec);
+        assert!(code.contains(r#"export interface User {
+  id: number;
+  status?: "active" | "archived";
+  "street-name"?: string | null;
+  tags?: {
+    name?: string;
+  }[];
+}"#), "{}", code);
+        assert!(code.contains("export type Pet = Cat | Dog;"), "{}", code);
+        assert!(code.contains("export type GetPetsResponse = Pet[];"), "{}", code);
+        assert!(code.contains("export type CreateUserRequest = {\n  name: string;\n};"), "{}", code);
+        assert!(code.contains("export type CreateUserResponse = User;"), "{}", code);
+        assert!(!code.contains("export type GetPetsResponse = GetPetsResponse"), "{}", code);
+    }
+
+    #[test]
+    fn test_emit_typescript_name_collision() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "user": {"type": "object", "properties": {"id": {"type": "integer"}}},
+                "User": {"allOf": [{"$ref": "#/components/schemas/user"}, {"type": "object", "properties": {
+                    "friend": {"$ref": "#/components/schemas/User"},
+                }}]},
+                "record": {"type": "object", "properties": {"id": {"type": "integer"}}},
+            }},
+        }))
+        .unwrap();
+        let code = emit_typescript(&spec);
+        assert!(code.contains("export interface User {\n  id?: number;\n}"), "{}", code);
+        assert!(code.contains("export interface User2 extends User {\n  friend?: User2;\n}"), "{}", code);
+        assert!(code.contains("export interface Record2 {\n  id?: number;\n}"), "{}", code);
+    }
+}
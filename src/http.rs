@@ -15,6 +15,7 @@ pub use response::*;
 
 pub type Header = (String, String);
 pub type Query = (String, String);
+pub type Cookie = (String, String);
 
 #[derive(Debug)]
 pub struct RequestResponse {
@@ -67,6 +68,8 @@ pub enum ParameterType {
 pub struct PathParameter {
     pub name: String,
     pub typ: ParameterType,
+    /// The value captured in the request, e.g. `"123"`
+    pub value: String,
 }
 
 #[derive(Debug)]
@@ -108,7 +111,9 @@ fn plural(s: &str) -> bool {
 }
 
 impl RequestInfo {
-    fn from_request(request: &Request) -> Self {
+    /// The operation and object names for a request, or `None` for paths without an object
+    /// name, e.g. `/`.
+    pub fn try_from_request(request: &Request) -> Option<Self> {
         // As in, fetch one or fetch many
         let mut gets_many = false;
         let mut path_parameters = Vec::new();
@@ -123,6 +128,7 @@ impl RequestInfo {
                     path_parameters.push(PathParameter {
                         name: "id".to_string(),
                         typ: ParameterType::Integer,
+                        value: s.clone(),
                     });
                     "{id}".to_string()
                 } else {
@@ -154,23 +160,27 @@ impl RequestInfo {
         if gets_many {
             pluralize(&mut operation_id);
         }
-        let object_name = object_name.expect(&format!("No object name found in path {:?}", &request.url.as_str()));
-
-        let object_name = extract_object_name(&object_name).unwrap().to_case(Case::Pascal);
+        let object_name = extract_object_name(&object_name?)?.to_case(Case::Pascal);
         let mut response_object_name = operation_id.to_case(Case::Pascal);
         response_object_name.push_str("Response");
-        Self {
+        Some(Self {
             path,
             path_parameters,
             object_name,
             operation_id,
             response_object_name,
             method: request.method.clone(),
-        }
+        })
     }
 }
 
-fn ignore_header(h: &str) -> bool {
+/// Headers that are part of the transport or the browser rather than the API.
+/// Matched case-insensitively, since HTTP/1.1 captures keep the original casing.
+pub fn ignore_header(h: &str) -> bool {
+    let h = h.to_ascii_lowercase();
+    if h.starts_with(':') || h.starts_with("sec-") {
+        return true;
+    }
     [
         "content-length",
         "content-type",
@@ -199,13 +209,81 @@ fn ignore_header(h: &str) -> bool {
         "sec-fetch-dest",
         "sec-fetch-mode",
         "sec-fetch-site",
-    ].contains(&h)
+        "host",
+        "connection",
+        "keep-alive",
+        "origin",
+        "pragma",
+        "cache-control",
+        "priority",
+        "te",
+        "upgrade-insecure-requests",
+        "if-none-match",
+        "if-modified-since",
+        "x-requested-with",
+    ].contains(&h.as_str())
+}
+
+/// Response headers that describe the transport, caching infrastructure or browser security
+/// policy rather than the API, in addition to [ignore_header].
+pub fn ignore_response_header(h: &str) -> bool {
+    let h = h.to_ascii_lowercase();
+    if h.starts_with("cf-") || h.starts_with("x-amz-cf-") || h.starts_with("cross-origin-") {
+        return true;
+    }
+    [
+        "age",
+        "alt-svc",
+        "content-encoding",
+        "content-security-policy",
+        "content-security-policy-report-only",
+        "expect-ct",
+        "expires",
+        "nel",
+        "permissions-policy",
+        "referrer-policy",
+        "report-to",
+        "server-timing",
+        "set-cookie",
+        "strict-transport-security",
+        "timing-allow-origin",
+        "transfer-encoding",
+        "vary",
+        "via",
+        "x-cache",
+        "x-cache-hits",
+        "x-content-type-options",
+        "x-frame-options",
+        "x-powered-by",
+        "x-served-by",
+        "x-timer",
+        "x-xss-protection",
+    ].contains(&h.as_str())
 }
 
 impl From<Entries> for RequestResponse {
     fn from(entry: Entries) -> Self {
+        let url = entry.request.url.clone();
+        Self::try_from_entry(entry).unwrap_or_else(|| panic!("No object name found in path {:?}", url))
+    }
+}
+
+impl RequestResponse {
+    /// Like `RequestResponse::from`, but `None` for requests that don't name an object, e.g.
+    /// `GET /` or `/favicon.ico`, and for invalid URLs.
+    pub fn try_from_entry(entry: Entries) -> Option<Self> {
+        let header = |name: &str| entry.request.headers.iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.clone());
+        let authorization = header("authorization");
+        // Some HAR exporters leave `cookies` empty, so fall back to the Cookie header.
+        let cookies = if entry.request.cookies.is_empty() {
+            header("cookie").as_deref().map(parse_cookie_header).unwrap_or_default()
+        } else {
+            entry.request.cookies.into_iter().map(|c| (c.name, c.value)).collect()
+        };
         let mut request = Request {
-            url: Url::parse(&entry.request.url).unwrap(),
+            url: Url::parse(&entry.request.url).ok()?,
             headers: entry.request.headers.into_iter()
                 .map(|h| (h.name, h.value))
                 .filter(|(h, _)| !ignore_header(h))
@@ -231,8 +309,16 @@ impl From<Entries> for RequestResponse {
                         mime,
                         content,
                     }
-                })
+                }),
+            authorization,
+            cookies,
         };
+        let mime = entry.response.content.mime_type.unwrap_or_default();
+        let set_cookies = entry.response.headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("set-cookie"))
+            .map(|h| h.value.clone())
+            .collect();
         let data = match entry.response.content.text {
             Some(text) => {
                 serde_json::from_str(&text).unwrap_or_else(|_| Value::String(text))
@@ -240,31 +326,104 @@ impl From<Entries> for RequestResponse {
             None => Value::Null,
         };
         let mut response = Response {
+            status: entry.response.status as u16,
             data,
+            mime,
             headers: entry.response.headers
                 .into_iter()
                 .map(|h| (h.name, h.value))
-                .filter(|(h, _)| !ignore_header(h))
+                .filter(|(h, _)| !ignore_header(h) && !ignore_response_header(h))
                 .collect(),
+            set_cookies,
         };
-        let info = RequestInfo::from_request(&request);
-        RequestResponse {
+        let info = RequestInfo::try_from_request(&request)?;
+        Some(RequestResponse {
             info,
             request,
             response,
-        }
+        })
     }
 }
 
+pub(crate) fn parse_cookie_header(header: &str) -> Vec<Cookie> {
+    header.split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
 
 pub fn read_har(path: &str) -> Result<Vec<Entries>> {
+    Ok(read_har_log(path)?.entries)
+}
+
+/// Like [read_har], but also returns the metadata of the capture (e.g. `creator`).
+pub fn read_har_log(path: &str) -> Result<har::v1_2::Log> {
     let har = har::from_path(path)?;
-    let entries = match har.log {
-        Spec::V1_2(har::v1_2::Log { entries, .. }) => entries,
+    match har.log {
+        Spec::V1_2(log) => Ok(log),
         Spec::V1_3(har::v1_3::Log { .. }) => unimplemented!(),
-    };
+    }
+}
 
-    Ok(entries)
+/// Build a HAR entry for tests, with the query string taken from `url` and `response` as the
+/// JSON response body.
+#[cfg(test)]
+pub(crate) fn test_entry(method: &str, url: &str, response: Value) -> Entries {
+    let query = Url::parse(url).unwrap()
+        .query_pairs()
+        .map(|(name, value)| serde_json::json!({"name": name, "value": value}))
+        .collect::<Vec<_>>();
+    serde_json::from_value(serde_json::json!({
+        "startedDateTime": "2023-08-01T12:00:00.000Z",
+        "time": 0.0,
+        "request": {
+            "method": method,
+            "url": url,
+            "httpVersion": "HTTP/2",
+            "cookies": [],
+            "headers": [],
+            "queryString": query,
+            "headersSize": -1,
+        },
+        "response": {
+            "status": 200,
+            "statusText": "OK",
+            "httpVersion": "HTTP/2",
+            "cookies": [],
+            "headers": [],
+            "content": {
+                "mimeType": "application/json",
+                "text": response.to_string(),
+            },
+        },
+        "cache": {},
+        "timings": {"send": 0.0, "wait": 0.0, "receive": 0.0},
+    })).unwrap()
+}
+
+/// Like [test_entry], with the given request and response headers.
+#[cfg(test)]
+pub(crate) fn test_entry_with_headers(
+    method: &str,
+    url: &str,
+    response: Value,
+    request_headers: &[(&str, &str)],
+    response_headers: &[(&str, &str)],
+) -> Entries {
+    let headers = |headers: &[(&str, &str)]| {
+        headers
+            .iter()
+            .map(|(name, value)| har::v1_2::Headers {
+                name: name.to_string(),
+                value: value.to_string(),
+                comment: None,
+            })
+            .collect()
+    };
+    let mut entry = test_entry(method, url, response);
+    entry.request.headers = headers(request_headers);
+    entry.response.headers = headers(response_headers);
+    entry
 }
 
 #[cfg(test)]
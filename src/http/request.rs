@@ -1,6 +1,6 @@
 use serde_json::Value;
 use url::Url;
-use crate::http::{Header, Query};
+use crate::http::{Cookie, Header, Query};
 
 #[derive(Debug)]
 pub struct Request {
@@ -9,6 +9,9 @@ pub struct Request {
     pub query: Vec<Query>,
     pub method: String,
     pub body: Option<RequestBody>,
+    /// The `Authorization` header, which is kept out of `headers`.
+    pub authorization: Option<String>,
+    pub cookies: Vec<Cookie>,
 }
 
 #[derive(Debug)]
@@ -5,7 +5,17 @@ use crate::http::Header;
 
 #[derive(Debug)]
 pub struct Response {
+    pub status: u16,
     pub data: Value,
+    /// The media type of the body, e.g. `application/json`. Empty if unknown.
+    pub mime: String,
     pub headers: Vec<Header>,
+    /// The `Set-Cookie` values, which aren't documented and so aren't part of `headers`.
+    pub set_cookies: Vec<String>,
 }
 
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
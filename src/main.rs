@@ -18,8 +18,12 @@ use serde_json::{Map, Value};
 
 use command::*;
 
+mod emit;
 mod http;
+mod mock;
 mod openapi;
+mod record;
+mod replay;
 mod command;
 
 #[derive(Parser)]
@@ -36,18 +40,34 @@ struct Cli {
 pub enum Command {
     /// Generate an OpenAPI spec from a HAR file
     Generate(Generate),
-    // Merge(Merge),
     /// Merge multiple Har logs into one
     Merge(MergeHar),
+    /// Merge multiple OpenAPI specs into one
+    MergeSpec(Merge),
     /// Filter
     Filter(Filter),
+    /// Compare two OpenAPI specs (or HAR files) and classify the changes
+    Diff(Diff),
+    /// Check the requests and responses of a HAR file against an OpenAPI spec
+    Validate(Validate),
+    /// Report which operations, status codes, parameters and properties of a spec HAR files exercise
+    Coverage(Coverage),
+    /// Serve recorded responses from HAR files on localhost
+    Mock(Mock),
+    /// Re-send the requests of a HAR file to a server and compare the responses with the recorded ones
+    Replay(Replay),
+    /// Run a local proxy that records every exchange to a HAR file
+    Record(Record),
+    /// Generate client code from the inferred schemas
+    Emit(Emit),
 }
 
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .compact()
         .without_time()
-        .with_ansi(std::io::stdin().is_terminal())
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_writer(std::io::stderr)
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
@@ -57,6 +77,13 @@ fn main() -> Result<()> {
         Command::Generate(g) => g.run(),
         Command::Merge(m) => m.run(),
         Command::Filter(f) => f.run(),
-        Command::Merge(m) => m.run(),
+        Command::MergeSpec(m) => m.run(),
+        Command::Diff(d) => d.run(),
+        Command::Validate(v) => v.run(),
+        Command::Coverage(c) => c.run(),
+        Command::Mock(m) => m.run(),
+        Command::Replay(r) => r.run(),
+        Command::Record(r) => r.run(),
+        Command::Emit(e) => e.run(),
     }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+use crate::http::{Request, RequestInfo, RequestResponse, Response};
+use crate::openapi::example;
+use crate::openapi::validate::match_request;
+use openapiv3 as oa;
+use serde_json::{json, Value};
+
+/// Answers requests with recorded responses, falling back to examples built from a spec.
+pub struct Mock {
+    recorded: Vec<RequestResponse>,
+    spec: Option<oa::OpenAPI>,
+}
+
+/// How closely a recorded request resembles an incoming one with the same path template and
+/// method: the same concrete path counts most, then each query parameter with the same value,
+/// then each one that is merely present in both.
+fn score(recorded: &Request, request: &Request) -> i64 {
+    let mut score = 0;
+    if recorded.url.path() == request.url.path() {
+        score += 100;
+    }
+    for (name, value) in &request.query {
+        match recorded.query.iter().find(|(n, _)| n == name) {
+            Some((_, v)) if v == value => score += 10,
+            Some(_) => score += 1,
+            None => score -= 1,
+        }
+    }
+    score - recorded.query.iter().filter(|(n, _)| !request.query.iter().any(|(q, _)| q == n)).count() as i64
+}
+
+impl Mock {
+    pub fn new(recorded: Vec<RequestResponse>, spec: Option<oa::OpenAPI>) -> Self {
+        Self { recorded, spec }
+    }
+
+    /// The best matching recorded response, a response generated from the spec, or a 404.
+    pub fn respond(&self, request: &Request) -> Response {
+        if let Some(rr) = self.find_recorded(request) {
+            return Response {
+                status: rr.response.status,
+                data: rr.response.data.clone(),
+                mime: rr.response.mime.clone(),
+                headers: rr.response.headers.clone(),
+                set_cookies: rr.response.set_cookies.clone(),
+            };
+        }
+        if let Some(response) = self.spec.as_ref().and_then(|spec| generate_response(spec, request)) {
+            return response;
+        }
+        Response {
+            status: 404,
+            data: json!({"error": format!("No recorded or documented response for {} {}", request.method, request.url.path())}),
+            mime: "application/json".to_string(),
+            headers: Vec::new(),
+            set_cookies: Vec::new(),
+        }
+    }
+
+    fn find_recorded(&self, request: &Request) -> Option<&RequestResponse> {
+        let info = RequestInfo::try_from_request(request)?;
+        self.recorded
+            .iter()
+            .filter(|rr| rr.path() == info.path && rr.method().eq_ignore_ascii_case(&request.method))
+            // max_by_key keeps the last of equal scores; prefer the first recording.
+            .rev()
+            .max_by_key(|rr| score(&rr.request, request))
+    }
+}
+
+/// A response for the operation matching `request`, using its first documented success status
+/// (or the first documented one) and an example of its JSON body.
+fn generate_response(spec: &oa::OpenAPI, request: &Request) -> Option<Response> {
+    let (_, item, _) = match_request(spec, request.url.path())?;
+    let method = request.method.to_lowercase();
+    let (_, op) = item.iter().find(|(m, _)| *m == method)?;
+    let responses = &op.responses;
+    let (status, response) = responses
+        .responses
+        .iter()
+        .find(|(status, _)| matches!(status, oa::StatusCode::Code(200..=299) | oa::StatusCode::Range(2)))
+        .or_else(|| responses.responses.first())
+        .map(|(status, response)| match status {
+            oa::StatusCode::Code(code) => (*code, response),
+            oa::StatusCode::Range(range) => (range * 100, response),
+        })
+        .or_else(|| Some((200, responses.default.as_ref()?)))?;
+    let response = response.resolve(spec).ok()?;
+    let (mime, data) = response
+        .content
+        .iter()
+        .find(|(mime, _)| mime.contains("json"))
+        .map(|(mime, media)| (mime.clone(), example::media_example(spec, media)))
+        .unwrap_or_default();
+    Some(Response {
+        status,
+        data,
+        mime,
+        headers: Vec::new(),
+        set_cookies: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_entry;
+
+    fn request(url: &str) -> Request {
+        RequestResponse::from(test_entry("GET", url, Value::Null)).request
+    }
+
+    #[test]
+    fn test_respond() {
+        let recorded = [
+            test_entry("GET", "https://api.example.com/users/1", json!({"id": 1})),
+            test_entry("GET", "https://api.example.com/users/2", json!({"id": 2})),
+            test_entry("GET", "https://api.example.com/users?page=1", json!([{"id": 1}])),
+            test_entry("GET", "https://api.example.com/users?page=2", json!([{"id": 2}])),
+        ]
+        .map(RequestResponse::from)
+        .into_iter()
+        .collect();
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/teams/{id}": {"get": {"responses": {
+                "404": {"description": ""},
+                "200": {"description": "", "content": {"application/json": {"schema": {
+                    "type": "object", "properties": {"name": {"type": "string"}},
+                }}}},
+            }}}},
+        }))
+        .unwrap();
+        let mock = Mock::new(recorded, Some(spec));
+        assert_eq!(mock.respond(&request("http://localhost/users/2")).data, json!({"id": 2}));
+        assert_eq!(mock.respond(&request("http://localhost/users/3")).data, json!({"id": 1}));
+        assert_eq!(mock.respond(&request("http://localhost/users?page=2")).data, json!([{"id": 2}]));
+        let generated = mock.respond(&request("http://localhost/teams/5"));
+        assert_eq!((generated.status, generated.data), (200, json!({"name": "string"})));
+        assert_eq!(mock.respond(&request("http://localhost/projects")).status, 404);
+    }
+}
@@ -1,7 +1,16 @@
+pub mod coverage;
+pub mod diff;
+pub mod example;
+pub mod merge;
 pub mod operation;
 mod parameter;
 pub(crate) mod response;
-mod schema;
+pub(crate) mod schema;
+pub mod security;
+pub mod update;
+pub mod v2;
+pub mod validate;
+pub mod v3_1;
 
 use crate::http::{singular, Request, RequestResponse};
 use anyhow::anyhow;
@@ -18,7 +27,7 @@ use std::cell::OnceCell;
 use std::fmt::Formatter;
 use std::ops::DerefMut;
 use std::str::FromStr;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 /// Takes a name and returns the singular version of it
 /// e.g. Vendors -> Vendor
@@ -107,14 +116,22 @@ impl std::fmt::Debug for Lu<oa::ReferenceOr<oa::Schema>> {
     }
 }
 
+/// `object_name` is the name of the value being described (e.g. the singular of its key), and
+/// `parent` is the schema name of the object containing it, used to disambiguate conflicting
+/// component names.
 fn create_schema(
     components: &mut oa::Components,
     value: &Value,
     object_name: Option<&str>,
+    parent: Option<&str>,
     rr: &RequestResponse,
 ) -> Result<oa::Schema> {
-    let s = match value {
-        Value::Null => oa::Schema::new_object(),
+    let mut s = match value {
+        Value::Null => {
+            let mut s = oa::Schema::new_object();
+            s.data.nullable = true;
+            s
+        }
         Value::Bool(_) => oa::Schema::new_bool(),
         Value::Number(n) => {
             let mut s = if n.is_f64() {
@@ -164,33 +181,56 @@ fn create_schema(
             }
             s
         }
-        Value::Array(inner) => {
-            // println!("Array: {}", object_name);
-            // let object_name = rr.object_name();
-            // First add the inner schema to the components
-            let inner = if inner.len() == 0 {
-                oa::Schema::new_object()
-            } else {
-                create_schema(components, &inner[0], object_name, rr).unwrap()
-            };
-            if is_primitive(&inner) {
-                oa::Schema::new_array(inner)
-            } else {
+        Value::Array(inner) => match find_discriminator(inner) {
+            Some(discriminator) => {
+                let name = create_polymorphic_schema(components, inner, &discriminator, object_name, parent, rr);
+                oa::Schema::new_array(RefOr::schema_ref(&name))
+            }
+            None => {
+                // println!("Array: {}", object_name);
+                // let object_name = rr.object_name();
+                // First add the inner schema to the components
+                let inner = if inner.len() == 0 {
+                    oa::Schema::new_object()
+                } else {
+                    create_schema(components, &inner[0], object_name, parent, rr).unwrap()
+                };
+                if is_primitive(&inner) {
+                    oa::Schema::new_array(inner)
+                } else {
+                    let object_name = object_name
+                        .unwrap_or_else(|| rr.object_name())
+                        .to_case(Case::Pascal);
+                    debug!(name=%object_name, url=rr.request.url.as_str(), "Registering array item schema");
+                    let object_name = schema::register_schema(components, &object_name, inner, parent);
+                    // Then return an array, which references the inner schema
+                    oa::Schema::new_array(RefOr::schema_ref(&object_name))
+                }
+            }
+        },
+        Value::Object(map) if is_dictionary(map) => {
+            // The keys are data (e.g. ids), so describe the values once and use additionalProperties.
+            let mut values = map
+                .values()
+                .filter_map(|v| create_schema(components, v, object_name, parent, rr).ok());
+            let mut inner = values.next().unwrap_or_else(oa::Schema::new_object);
+            for value in values {
+                schema::merge_schema(&mut inner, value);
+            }
+            debug!(keys = map.len(), url = rr.request.url.as_str(), "Detected dictionary object");
+            if use_reference(&inner) {
                 let object_name = object_name
                     .unwrap_or_else(|| rr.object_name())
                     .to_case(Case::Pascal);
-                let map = components.schemas.deref_mut();
-                if let Some(e) = map.insert(object_name.clone(), inner.into()) {
-                    warn!(name=%object_name, existing=?Lu(e), "Schema already exists. TODO consider checking schemas for equality, panicking if not equal.");
-                } else {
-                    info!(name=%object_name, url=rr.request.url.as_str(), "Added schema");
-                }
-                // Then return an array, which references the inner schema
-                oa::Schema::new_array(RefOr::schema_ref(&object_name))
+                let object_name = schema::register_schema(components, &object_name, inner, parent);
+                oa::Schema::new_map(RefOr::schema_ref(&object_name))
+            } else {
+                oa::Schema::new_map(inner)
             }
         }
         Value::Object(map) => {
             let mut s = oa::Schema::new_object();
+            let own_name = object_name.map(schema_name);
             for (key, value) in map {
                 let schema_name = if value.is_array() && key.to_lowercase() == "list" {
                     Some(rr.object_name().to_string())
@@ -198,7 +238,7 @@ fn create_schema(
                     Some(singular(key))
                 };
                 let schema_name = schema_name.as_ref().map(|s| s.as_str());
-                let Ok(schema) = create_schema(components, value, schema_name, rr) else {
+                let Ok(schema) = create_schema(components, value, schema_name, own_name.as_deref(), rr) else {
                     continue;
                 };
                 s.add_required(key);
@@ -206,12 +246,8 @@ fn create_schema(
                     let schema_name = schema_name
                         .unwrap_or_else(|| rr.object_name())
                         .to_case(Case::Pascal);
-                    let map = components.schemas.deref_mut();
-                    if let Some(o) = map.insert(schema_name.clone(), schema.into()) {
-                        warn!(name=%schema_name, existing=?Lu(o), "Schema already exists");
-                    } else {
-                        info!(name=%schema_name, url=rr.request.url.as_str(), "Added schema");
-                    }
+                    debug!(name=%schema_name, url=rr.request.url.as_str(), "Registering object schema");
+                    let schema_name = schema::register_schema(components, &schema_name, schema, own_name.as_deref());
                     s.properties_mut().insert(key, RefOr::schema_ref(&schema_name));
                 } else {
                     s.properties_mut().insert(key, schema);
@@ -220,18 +256,144 @@ fn create_schema(
             s
         }
     };
+    if matches!(value, Value::Bool(_) | Value::Number(_) | Value::String(_)) {
+        // Sensitive values are removed later, by example::redact_schema_examples.
+        s.data.example = Some(value.clone());
+    }
     Ok(s)
 }
 
+/// Property names that can be a discriminator, in order of preference.
+const DISCRIMINATOR_NAMES: [&str; 6] = ["type", "kind", "object", "object_type", "event", "__typename"];
+
+/// Find a property whose value partitions the objects in `items` into distinct shapes, e.g. the
+/// `type` of a feed of mixed events. Only discriminator-like names are considered, the property
+/// has to be a string in every item, and its values have to look like identifiers. Items with
+/// the same value must have the same shape, and each value must select a different set of keys.
+fn find_discriminator(items: &[Value]) -> Option<String> {
+    static IDENTIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_.\-]{0,39}$").unwrap());
+    if items.len() < 2 {
+        return None;
+    }
+    let objects = items.iter().map(Value::as_object).collect::<Option<Vec<_>>>()?;
+    DISCRIMINATOR_NAMES
+        .into_iter()
+        .filter(|k| objects.iter().all(|o| o.get(*k).and_then(Value::as_str).is_some_and(|v| IDENTIFIER.is_match(v))))
+        .find(|key| {
+            let groups = objects.iter().into_group_map_by(|o| o[*key].as_str().unwrap());
+            // Unique values on more than two items look like names or ids rather than types.
+            if groups.len() < 2 || (groups.len() > 2 && groups.values().all(|g| g.len() == 1)) {
+                return false;
+            }
+            let consistent = groups.values().all(|g| g.iter().all(|o| shares_keys(g[0], o)));
+            let shapes = groups
+                .values()
+                .map(|g| g.iter().flat_map(|o| o.keys()).sorted().dedup().collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            consistent && shapes.iter().all_unique()
+        })
+        .map(String::from)
+}
+
+/// Register one component per discriminator value, plus a `oneOf` component with a
+/// `discriminator` mapping over them, and return the name of the `oneOf` component.
+fn create_polymorphic_schema(
+    components: &mut oa::Components,
+    items: &[Value],
+    discriminator: &str,
+    object_name: Option<&str>,
+    parent: Option<&str>,
+    rr: &RequestResponse,
+) -> String {
+    let base_name = object_name
+        .unwrap_or_else(|| rr.object_name())
+        .to_case(Case::Pascal);
+    let groups = items.iter().into_group_map_by(|v| v[discriminator].as_str().unwrap().to_string());
+    let mut one_of = Vec::new();
+    let mut mapping = indexmap! {};
+    for (value, group) in groups.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+        let mut variant = group
+            .iter()
+            .filter_map(|v| create_schema(components, v, object_name, parent, rr).ok())
+            .reduce(|mut a, b| {
+                schema::merge_schema(&mut a, b);
+                a
+            })
+            .unwrap();
+        variant
+            .properties_mut()
+            .insert(discriminator, oa::Schema::new_str_enum(vec![value.clone()]));
+        let variant_name = format!("{}{}", schema_name(&value), base_name);
+        let variant_name = schema::register_schema(components, &variant_name, variant, Some(&base_name));
+        info!(name=%variant_name, discriminator, value=%value, "Added polymorphic variant");
+        let reference = format!("#/components/schemas/{}", variant_name);
+        one_of.push(RefOr::ref_(&reference));
+        mapping.insert(value, reference);
+    }
+    let mut s = oa::Schema::new_one_of(one_of);
+    s.data.discriminator = Some(oa::Discriminator {
+        property_name: discriminator.to_string(),
+        mapping,
+        extensions: Default::default(),
+    });
+    schema::register_schema(components, &base_name, s, parent)
+}
+
+/// Objects with at least this many keys are treated as dictionaries if their values are
+/// homogeneous objects or arrays.
+const DICTIONARY_MIN_KEYS: usize = 20;
+
+/// Detect objects whose keys are dynamic (e.g. `{"123": {...}, "456": {...}}`) rather than
+/// property names: either every key is an id (numeric or UUID), or there are many keys and the
+/// values are objects or arrays. In both cases the values must all have the same shape.
+///
+/// Many keys with primitive values are not enough on their own, since ordinary records often
+/// have dozens of string fields.
+fn is_dictionary(map: &Map<String, Value>) -> bool {
+    static UUID: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$").unwrap()
+    });
+    if map.is_empty() {
+        return false;
+    }
+    let id_keys = map
+        .keys()
+        .all(|k| (!k.is_empty() && k.chars().all(|c| c.is_ascii_digit())) || UUID.is_match(k));
+    let values = map.values().filter(|v| !v.is_null()).collect::<Vec<_>>();
+    if !id_keys && (map.len() < DICTIONARY_MIN_KEYS || !values.iter().all(|v| v.is_object() || v.is_array())) {
+        return false;
+    }
+    let [first, rest @ ..] = values.as_slice() else {
+        return id_keys;
+    };
+    rest.iter().all(|v| same_shape(first, v))
+}
+
+/// Whether two JSON values look like samples of the same type. Objects must share at least half
+/// of their keys.
+fn same_shape(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => shares_keys(a, b),
+        (Value::Number(_), Value::Number(_))
+        | (Value::String(_), Value::String(_))
+        | (Value::Bool(_), Value::Bool(_))
+        | (Value::Array(_), Value::Array(_)) => true,
+        _ => false,
+    }
+}
+
+/// Whether two objects share at least half of their keys.
+fn shares_keys(a: &Map<String, Value>, b: &Map<String, Value>) -> bool {
+    let shared = a.keys().filter(|k| b.contains_key(*k)).count();
+    let total = a.len() + b.len() - shared;
+    total == 0 || shared * 2 >= total
+}
+
 // (Value, &mut Components) -> oa::Schema
 fn add_response_schemas(components: &mut oa::Components, rr: &RequestResponse) -> Result<()> {
     let response_data = &rr.response.data;
-    let schema = create_schema(components, &response_data, None, rr)?;
-    let object_name = rr.response_object_name();
-    let map = components.schemas.deref_mut();
-    if let Some(old) = map.insert(object_name.to_string(), schema.into()) {
-        warn!(name=%object_name, existing=?Lu(old), "Schema already exists");
-    }
+    let schema = create_schema(components, response_data, None, None, rr)?;
+    schema::upsert_schema(components, rr.response_object_name(), schema);
     Ok(())
 }
 
@@ -322,7 +484,7 @@ mod tests {
             .unwrap()
             .as_item()
             .unwrap();
-        let props = schema.properties().unwrap();
+        let props = schema.properties();
         let s = serde_yaml::to_string(&spec.components).unwrap();
         println!("{}", s);
         assert!(spec.schemas.get("Address").is_some());
@@ -331,7 +493,6 @@ mod tests {
         let schema = spec.schemas.get("External").unwrap().as_item().unwrap();
         let users = schema
             .properties()
-            .unwrap()
             .get("users")
             .unwrap()
             .as_item()
@@ -364,7 +525,6 @@ mod tests {
         let schema = spec.schemas.get("Item").unwrap().as_item().unwrap();
         let invoice = schema
             .properties()
-            .unwrap()
             .get("invoice")
             .unwrap()
             .as_item()
@@ -380,7 +540,6 @@ mod tests {
         );
         let client_total_balance = schema
             .properties()
-            .unwrap()
             .get("client_total_balance")
             .unwrap()
             .as_item()
@@ -395,7 +554,6 @@ mod tests {
 
         let invoice_name = schema
             .properties()
-            .unwrap()
             .get("invoice_name")
             .unwrap()
             .as_item()
@@ -432,7 +590,6 @@ mod tests {
             .unwrap();
         let permissions = res
             .properties()
-            .unwrap()
             .get("permissions")
             .unwrap()
             .as_item()
@@ -451,4 +608,81 @@ mod tests {
         ));
         Ok(())
     }
+
+    #[test]
+    fn test_dictionary_objects_use_additional_properties() -> Result<()> {
+        let data = serde_json::json!({
+            "users": {
+                "123": {"name": "Ann", "email": "ann@example.com"},
+                "456": {"name": "Bob", "email": "bob@example.com", "phone": "555"},
+            },
+            "settings": {"theme": "dark", "locale": "en"},
+        });
+        let rr = RequestResponse::from(crate::http::test_entry("GET", "https://example.com/users", data));
+        let mut spec = oa::OpenAPI::default();
+        create_schema_for_responses(&[rr], &mut spec.components)?;
+
+        let res = spec.schemas.get2("GetUsersResponse").unwrap();
+        let users = res.properties().get2("users").unwrap();
+        let oa::SchemaKind::Type(oa::Type::Object(users)) = &users.kind else {
+            panic!("expected object");
+        };
+        assert!(users.properties.is_empty());
+        let Some(oa::AdditionalProperties::Schema(value)) = &users.additional_properties else {
+            panic!("expected additionalProperties schema");
+        };
+        assert_eq!(value.as_ref_str(), Some("#/components/schemas/User"));
+        let user = spec.schemas.get2("User").unwrap();
+        assert_eq!(user.properties().len(), 3);
+        assert_eq!(user.required().len(), 2);
+
+        assert!(spec.schemas.get("Setting").is_some(), "settings is a regular object");
+
+        let record = (0..DICTIONARY_MIN_KEYS)
+            .map(|i| (format!("field_{}", i), Value::from("value")))
+            .collect::<Map<_, _>>();
+        assert!(!is_dictionary(&record), "a wide record of strings is not a dictionary");
+        let lookup = (0..DICTIONARY_MIN_KEYS)
+            .map(|i| (format!("sku-{}", i), serde_json::json!({"price": i})))
+            .collect::<Map<_, _>>();
+        assert!(is_dictionary(&lookup));
+        Ok(())
+    }
+
+    #[test]
+    fn test_polymorphic_arrays_use_one_of() -> Result<()> {
+        let data = serde_json::json!([
+            {"id": 1, "type": "photo", "url": "https://example.com/1.png", "width": 100},
+            {"id": 2, "type": "comment", "text": "Nice", "author": "Ann"},
+            {"id": 3, "type": "photo", "url": "https://example.com/3.png", "width": 200},
+        ]);
+        let rr = RequestResponse::from(crate::http::test_entry("GET", "https://example.com/feed", data));
+        let mut spec = oa::OpenAPI::default();
+        create_schema_for_responses(&[rr], &mut spec.components)?;
+
+        let feed = spec.schemas.get2("Feed").unwrap();
+        let oa::SchemaKind::OneOf { one_of } = &feed.kind else {
+            panic!("expected oneOf, got {:?}", feed.kind);
+        };
+        assert_eq!(one_of.len(), 2);
+        let discriminator = feed.discriminator.as_ref().unwrap();
+        assert_eq!(discriminator.property_name, "type");
+        assert_eq!(discriminator.mapping["photo"], "#/components/schemas/PhotoFeed");
+        assert!(spec.schemas.get2("PhotoFeed").unwrap().properties().contains_key("width"));
+        assert!(spec.schemas.get2("CommentFeed").unwrap().properties().contains_key("text"));
+
+        let users = serde_json::json!([
+            {"id": 1, "username": "ann", "status": "active", "avatar": "https://example.com/ann.png"},
+            {"id": 2, "username": "bob", "status": "invited"},
+            {"id": 3, "username": "cat", "status": "active"},
+        ]);
+        assert_eq!(find_discriminator(users.as_array().unwrap()), None);
+        let events = serde_json::json!([
+            {"type": "click", "x": 1, "y": 2},
+            {"type": "click", "x": 3},
+            {"type": "scroll", "x": 1, "y": 2},
+        ]);
+        assert_eq!(find_discriminator(events.as_array().unwrap()), None, "same keys for every type");
+        Ok(())
+    }
 }
@@ -0,0 +1,393 @@
+use crate::http::RequestResponse;
+use crate::openapi::parameter::query_value;
+use crate::openapi::schema::resolve_schema;
+use crate::openapi::validate::{find_response, json_schema, match_request};
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::Write;
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Covered {
+    pub name: String,
+    pub hits: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperationCoverage {
+    /// e.g. `GET /users/{id}`
+    pub operation: String,
+    pub calls: usize,
+    /// Documented status codes, e.g. `200`, `4XX` or `default`.
+    pub status_codes: Vec<Covered>,
+    /// Parameters as `<location> <name>`, e.g. `query page`.
+    pub parameters: Vec<Covered>,
+    /// Response properties as `<status> <path>`, e.g. `200 items[].id`.
+    pub properties: Vec<Covered>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub category: &'static str,
+    pub covered: usize,
+    pub total: usize,
+    pub percent: f64,
+}
+
+impl Summary {
+    fn new(category: &'static str, items: impl Iterator<Item = bool>) -> Self {
+        let (covered, total) = items.fold((0, 0), |(covered, total), hit| (covered + hit as usize, total + 1));
+        let percent = if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 };
+        Summary {
+            category,
+            covered,
+            total,
+            percent,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    pub operations: Vec<OperationCoverage>,
+    /// Entries that matched no operation in the spec.
+    pub unmatched: usize,
+}
+
+/// Match each exchange to its operation in `spec` and count how often every documented
+/// operation, status code, parameter and response property was exercised.
+pub fn coverage(spec: &oa::OpenAPI, rrs: &[RequestResponse]) -> Coverage {
+    let mut operations = Vec::new();
+    let mut index = Vec::new();
+    for (template, item) in spec.paths.paths.iter().filter_map(|(path, item)| Some((path, item.as_item()?))) {
+        for (method, op) in item.iter() {
+            let parameters = item
+                .parameters
+                .iter()
+                .chain(&op.parameters)
+                .filter_map(|p| p.resolve(spec).ok())
+                .collect::<Vec<_>>();
+            operations.push(OperationCoverage {
+                operation: format!("{} {}", method.to_uppercase(), template),
+                calls: 0,
+                status_codes: documented_statuses(&op.responses),
+                parameters: parameters
+                    .iter()
+                    .map(|p| uncovered(format!("{} {}", parameter_location(p), p.name)))
+                    .collect(),
+                properties: response_properties(spec, &op.responses),
+            });
+            index.push((template.as_str(), method, op, parameters));
+        }
+    }
+    let mut unmatched = 0;
+    for rr in rrs {
+        let method = rr.method().to_lowercase();
+        let found = match_request(spec, rr.request.url.path())
+            .and_then(|(template, _, _)| index.iter().position(|(t, m, _, _)| *t == template && *m == method));
+        let Some(i) = found else {
+            unmatched += 1;
+            continue;
+        };
+        let (_, _, op, parameters) = &index[i];
+        let coverage = &mut operations[i];
+        coverage.calls += 1;
+        let status = status_key(&op.responses, rr.response.status);
+        hit(&mut coverage.status_codes, &status);
+        for (covered, param) in coverage.parameters.iter_mut().zip(parameters) {
+            let name = &param.name;
+            let present = match param.kind {
+                oa::ParameterKind::Path { .. } => true,
+                oa::ParameterKind::Query { .. } => query_value(Some(spec), &rr.request.query, param).is_some(),
+                oa::ParameterKind::Header { .. } => rr.request.headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)),
+                oa::ParameterKind::Cookie { .. } => rr.request.cookies.iter().any(|(n, _)| n == name),
+            };
+            covered.hits += present as usize;
+        }
+        let mut observed = HashSet::new();
+        observed_properties(&rr.response.data, "", &mut observed);
+        for property in &mut coverage.properties {
+            if let Some(path) = property.name.strip_prefix(&status).and_then(|p| p.strip_prefix(' ')) {
+                property.hits += observed.contains(path) as usize;
+            }
+        }
+    }
+    Coverage { operations, unmatched }
+}
+
+fn uncovered(name: String) -> Covered {
+    Covered { name, hits: 0 }
+}
+
+fn hit(items: &mut [Covered], name: &str) {
+    if let Some(item) = items.iter_mut().find(|i| i.name == name) {
+        item.hits += 1;
+    }
+}
+
+fn parameter_location(param: &oa::Parameter) -> &'static str {
+    match param.kind {
+        oa::ParameterKind::Query { .. } => "query",
+        oa::ParameterKind::Header { .. } => "header",
+        oa::ParameterKind::Path { .. } => "path",
+        oa::ParameterKind::Cookie { .. } => "cookie",
+    }
+}
+
+fn documented_statuses(responses: &oa::Responses) -> Vec<Covered> {
+    let mut statuses = responses.responses.keys().map(|s| uncovered(s.to_string())).collect::<Vec<_>>();
+    if responses.default.is_some() {
+        statuses.push(uncovered("default".to_string()));
+    }
+    statuses
+}
+
+/// The name of the documented response that `status` falls under, as in [documented_statuses].
+fn status_key(responses: &oa::Responses, status: u16) -> String {
+    if responses.responses.contains_key(&oa::StatusCode::Code(status)) {
+        status.to_string()
+    } else if responses.responses.contains_key(&oa::StatusCode::Range(status / 100)) {
+        oa::StatusCode::Range(status / 100).to_string()
+    } else if find_response(responses, status).is_some() {
+        "default".to_string()
+    } else {
+        status.to_string()
+    }
+}
+
+fn response_properties(spec: &oa::OpenAPI, responses: &oa::Responses) -> Vec<Covered> {
+    let statuses = documented_statuses(responses);
+    let all = responses.responses.values().chain(&responses.default);
+    let mut properties = Vec::new();
+    for (status, response) in statuses.iter().zip(all) {
+        let Some(schema) = response.resolve(spec).ok().and_then(|r| json_schema(&r.content, None)) else {
+            continue;
+        };
+        let mut paths = Vec::new();
+        schema_properties(spec, schema, "", &mut Vec::new(), &mut paths);
+        properties.extend(paths.into_iter().map(|p| uncovered(format!("{} {}", status.name, p))));
+    }
+    properties
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+/// Collect the property paths of a schema, e.g. `items[].id`. `refs` holds the references being
+/// expanded, so recursive schemas stop at the first repetition.
+fn schema_properties(spec: &oa::OpenAPI, schema: &RefOr<oa::Schema>, prefix: &str, refs: &mut Vec<String>, out: &mut Vec<String>) {
+    let Some(resolved) = resolve_schema(spec, schema) else {
+        warn!(reference = schema.as_ref_str().unwrap_or_default(), "Missing schema reference");
+        return;
+    };
+    if let Some(reference) = schema.as_ref_str() {
+        if refs.iter().any(|r| r == reference) {
+            return;
+        }
+        refs.push(reference.to_string());
+    }
+    use oa::SchemaKind::Type as T;
+    match &resolved.kind {
+        T(oa::Type::Object(o)) => {
+            for (name, property) in &o.properties {
+                let path = join(prefix, name);
+                if !out.contains(&path) {
+                    out.push(path.clone());
+                }
+                schema_properties(spec, property, &path, refs, out);
+            }
+        }
+        T(oa::Type::Array(a)) => {
+            if let Some(items) = &a.items {
+                schema_properties(spec, items, &format!("{}[]", prefix), refs, out);
+            }
+        }
+        oa::SchemaKind::AllOf { all_of: schemas } | oa::SchemaKind::OneOf { one_of: schemas } | oa::SchemaKind::AnyOf { any_of: schemas } => {
+            for schema in schemas {
+                schema_properties(spec, schema, prefix, refs, out);
+            }
+        }
+        _ => {}
+    }
+    if schema.as_ref_str().is_some() {
+        refs.pop();
+    }
+}
+
+/// Collect the property paths present in a value, in the same form as [schema_properties].
+fn observed_properties(value: &Value, prefix: &str, out: &mut HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            for (name, v) in map {
+                let path = join(prefix, name);
+                observed_properties(v, &path, out);
+                out.insert(path);
+            }
+        }
+        Value::Array(items) => {
+            let path = format!("{}[]", prefix);
+            for item in items {
+                observed_properties(item, &path, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Coverage {
+    pub fn summary(&self) -> Vec<Summary> {
+        let ops = &self.operations;
+        vec![
+            Summary::new("Operations", ops.iter().map(|o| o.calls > 0)),
+            Summary::new("Status codes", ops.iter().flat_map(|o| &o.status_codes).map(|c| c.hits > 0)),
+            Summary::new("Parameters", ops.iter().flat_map(|o| &o.parameters).map(|c| c.hits > 0)),
+            Summary::new("Response properties", ops.iter().flat_map(|o| &o.properties).map(|c| c.hits > 0)),
+        ]
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for summary in self.summary() {
+            writeln!(out, "{}: {}/{} ({:.1}%)", summary.category, summary.covered, summary.total, summary.percent).unwrap();
+        }
+        let count = |items: &[Covered]| format!("{}/{}", items.iter().filter(|c| c.hits > 0).count(), items.len());
+        writeln!(out).unwrap();
+        for op in self.operations.iter().filter(|o| o.calls > 0) {
+            writeln!(
+                out,
+                "{}: {} calls, status codes {}, parameters {}, response properties {}",
+                op.operation,
+                op.calls,
+                count(&op.status_codes),
+                count(&op.parameters),
+                count(&op.properties)
+            )
+            .unwrap();
+        }
+        let never = self.operations.iter().filter(|o| o.calls == 0).collect::<Vec<_>>();
+        if !never.is_empty() {
+            writeln!(out, "\nNever called:").unwrap();
+            for op in never {
+                writeln!(out, "  {}", op.operation).unwrap();
+            }
+        }
+        if self.unmatched > 0 {
+            writeln!(out, "\n{} requests matched no operation.", self.unmatched).unwrap();
+        }
+        out
+    }
+
+    /// An lcov-style tracefile with one record per operation: the operation is the function, and
+    /// its status codes, parameters and response properties are numbered lines, in that order.
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for op in &self.operations {
+            writeln!(out, "TN:").unwrap();
+            writeln!(out, "SF:{}", op.operation).unwrap();
+            writeln!(out, "FN:1,{}", op.operation).unwrap();
+            writeln!(out, "FNDA:{},{}", op.calls, op.operation).unwrap();
+            writeln!(out, "FNF:1").unwrap();
+            writeln!(out, "FNH:{}", (op.calls > 0) as usize).unwrap();
+            let items = op.status_codes.iter().chain(&op.parameters).chain(&op.properties).collect::<Vec<_>>();
+            for (i, item) in items.iter().enumerate() {
+                writeln!(out, "DA:{},{}", i + 1, item.hits).unwrap();
+            }
+            writeln!(out, "LF:{}", items.len()).unwrap();
+            writeln!(out, "LH:{}", items.iter().filter(|c| c.hits > 0).count()).unwrap();
+            writeln!(out, "end_of_record").unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_entry;
+    use serde_json::json;
+
+    #[test]
+    fn test_coverage() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {
+                "/users": {"get": {
+                    "parameters": [{"name": "page", "in": "query", "schema": {"type": "integer"}}],
+                    "responses": {
+                        "200": {"description": "", "content": {"application/json": {"schema": {
+                            "type": "array",
+                            "items": {"type": "object", "properties": {"id": {"type": "integer"}, "email": {"type": "string"}}},
+                        }}}},
+                        "404": {"description": ""},
+                    },
+                }},
+                "/teams": {"get": {"responses": {"200": {"description": ""}}}},
+            },
+        }))
+        .unwrap();
+        let rrs = [
+            test_entry("GET", "https://api.example.com/users", json!([{"id": 1}])),
+            test_entry("GET", "https://api.example.com/projects", json!([])),
+        ]
+        .map(RequestResponse::from);
+        let coverage = coverage(&spec, &rrs);
+        let users = &coverage.operations[0];
+        assert_eq!(users.calls, 1);
+        assert_eq!(users.status_codes, vec![
+            Covered { name: "200".to_string(), hits: 1 },
+            Covered { name: "404".to_string(), hits: 0 },
+        ]);
+        assert_eq!(users.parameters, vec![Covered { name: "query page".to_string(), hits: 0 }]);
+        assert_eq!(users.properties, vec![
+            Covered { name: "200 [].id".to_string(), hits: 1 },
+            Covered { name: "200 [].email".to_string(), hits: 0 },
+        ]);
+        assert_eq!(coverage.unmatched, 1);
+        let summary = coverage.summary().into_iter().map(|s| (s.covered, s.total)).collect::<Vec<_>>();
+        assert_eq!(summary, vec![(1, 2), (1, 3), (0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_coverage_grouped_query() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/items": {"get": {
+                "parameters": [
+                    {"name": "id", "in": "query", "schema": {"type": "array", "items": {"type": "integer"}}},
+                    {"name": "filter", "in": "query", "style": "deepObject", "schema": {"type": "object"}},
+                ],
+                "responses": {"200": {"description": ""}},
+            }}},
+        }))
+        .unwrap();
+        let rrs = [test_entry("GET", "https://api.example.com/items?id=1&id=2&filter[name]=a", json!([]))]
+            .map(RequestResponse::from);
+        let coverage = coverage(&spec, &rrs);
+        assert!(coverage.operations[0].parameters.iter().all(|p| p.hits == 1));
+    }
+
+    #[test]
+    fn test_coverage_dangling_reference() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/users": {"get": {"responses": {"200": {"description": "", "content": {"application/json": {"schema": {
+                "type": "object", "properties": {"id": {"type": "integer"}, "team": {"$ref": "#/components/schemas/Team"}},
+            }}}}}}}},
+        }))
+        .unwrap();
+        let coverage = coverage(&spec, &[]);
+        let properties = coverage.operations[0].properties.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(properties, vec!["200 id", "200 team"]);
+    }
+}
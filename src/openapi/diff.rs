@@ -0,0 +1,395 @@
+use crate::openapi::schema::resolve_schema;
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// What changed, e.g. `GET /users response 200 body.items[].email`.
+    pub location: String,
+    pub description: String,
+    /// Whether existing clients may stop working.
+    pub breaking: bool,
+}
+
+/// Schemas in requests and responses break clients in opposite ways: a new required request
+/// property breaks them, while a removed response property does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+struct Differ<'a> {
+    old: &'a oa::OpenAPI,
+    new: &'a oa::OpenAPI,
+    changes: Vec<Change>,
+    /// The pairs of schema references being compared, innermost last, so recursive schemas
+    /// terminate. A pair is compared again wherever else it is used.
+    refs: Vec<(String, String)>,
+}
+
+/// Compare two specs operation by operation, following schema references into the components.
+pub fn diff_specs(old: &oa::OpenAPI, new: &oa::OpenAPI) -> Vec<Change> {
+    let mut differ = Differ {
+        old,
+        new,
+        changes: Vec::new(),
+        refs: Vec::new(),
+    };
+    let old_ops = operations(old);
+    let new_ops = operations(new);
+    for (name, old_op) in &old_ops {
+        match new_ops.iter().find(|(n, _)| n == name) {
+            Some((_, new_op)) => differ.operation(name, old_op, new_op),
+            None => differ.push(ChangeKind::Removed, name, "operation removed", true),
+        }
+    }
+    for (name, _) in new_ops.iter().filter(|(n, _)| !old_ops.iter().any(|(o, _)| o == n)) {
+        differ.push(ChangeKind::Added, name, "operation added", false);
+    }
+    differ.changes
+}
+
+fn operations(spec: &oa::OpenAPI) -> Vec<(String, &oa::Operation)> {
+    spec.paths
+        .paths
+        .iter()
+        .filter_map(|(path, item)| Some((path, item.as_item()?)))
+        .flat_map(|(path, item)| item.iter().map(move |(method, op)| (format!("{} {}", method.to_uppercase(), path), op)))
+        .collect()
+}
+
+fn parameter_location(param: &oa::Parameter) -> &'static str {
+    match param.kind {
+        oa::ParameterKind::Query { .. } => "query",
+        oa::ParameterKind::Header { .. } => "header",
+        oa::ParameterKind::Path { .. } => "path",
+        oa::ParameterKind::Cookie { .. } => "cookie",
+    }
+}
+
+fn type_name(schema: &oa::Schema) -> &'static str {
+    match &schema.kind {
+        oa::SchemaKind::Type(oa::Type::String(_)) => "string",
+        oa::SchemaKind::Type(oa::Type::Number(_)) => "number",
+        oa::SchemaKind::Type(oa::Type::Integer(_)) => "integer",
+        oa::SchemaKind::Type(oa::Type::Object(_)) => "object",
+        oa::SchemaKind::Type(oa::Type::Array(_)) => "array",
+        oa::SchemaKind::Type(oa::Type::Boolean {}) => "boolean",
+        oa::SchemaKind::OneOf { .. } => "oneOf",
+        oa::SchemaKind::AllOf { .. } => "allOf",
+        oa::SchemaKind::AnyOf { .. } => "anyOf",
+        oa::SchemaKind::Not { .. } => "not",
+        oa::SchemaKind::Any(_) => "any",
+    }
+}
+
+fn string_enum(schema: &oa::Schema) -> &[String] {
+    match &schema.kind {
+        oa::SchemaKind::Type(oa::Type::String(s)) => &s.enumeration,
+        _ => &[],
+    }
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, kind: ChangeKind, location: &str, description: impl Into<String>, breaking: bool) {
+        self.changes.push(Change {
+            kind,
+            location: location.to_string(),
+            description: description.into(),
+            breaking,
+        });
+    }
+
+    fn parameters(spec: &'a oa::OpenAPI, op: &'a oa::Operation) -> Vec<&'a oa::Parameter> {
+        op.parameters.iter().filter_map(|p| p.resolve(spec).ok()).collect()
+    }
+
+    fn operation(&mut self, name: &str, old: &'a oa::Operation, new: &'a oa::Operation) {
+        let old_params = Self::parameters(self.old, old);
+        let new_params = Self::parameters(self.new, new);
+        let key = |p: &oa::Parameter| (p.name.to_ascii_lowercase(), parameter_location(p));
+        for old_param in &old_params {
+            let location = format!("{} {} parameter {}", name, parameter_location(old_param), old_param.name);
+            let Some(new_param) = new_params.iter().find(|p| key(p) == key(old_param)) else {
+                self.push(ChangeKind::Removed, &location, "parameter removed", true);
+                continue;
+            };
+            if !old_param.required && new_param.required {
+                self.push(ChangeKind::Changed, &location, "parameter became required", true);
+            } else if old_param.required && !new_param.required {
+                self.push(ChangeKind::Changed, &location, "parameter became optional", false);
+            }
+            if let (Some(old_schema), Some(new_schema)) = (old_param.schema(), new_param.schema()) {
+                self.schema(&location, old_schema, new_schema, Direction::Request);
+            }
+        }
+        for new_param in new_params.iter().filter(|p| !old_params.iter().any(|o| key(o) == key(p))) {
+            let location = format!("{} {} parameter {}", name, parameter_location(new_param), new_param.name);
+            let description = if new_param.required { "required parameter added" } else { "optional parameter added" };
+            self.push(ChangeKind::Added, &location, description, new_param.required);
+        }
+
+        let old_body = old.request_body.as_ref().and_then(|b| b.resolve(self.old).ok());
+        let new_body = new.request_body.as_ref().and_then(|b| b.resolve(self.new).ok());
+        let location = format!("{} request body", name);
+        match (old_body, new_body) {
+            (Some(_), None) => self.push(ChangeKind::Removed, &location, "request body removed", true),
+            (None, Some(body)) => {
+                let description = if body.required { "required request body added" } else { "optional request body added" };
+                self.push(ChangeKind::Added, &location, description, body.required);
+            }
+            (Some(old_body), Some(new_body)) => {
+                self.content(&location, &old_body.content, &new_body.content, Direction::Request);
+            }
+            (None, None) => {}
+        }
+
+        for (status, old_response) in &old.responses.responses {
+            let location = format!("{} response {}", name, status);
+            let Some(new_response) = new.responses.responses.get(status) else {
+                self.push(ChangeKind::Removed, &location, "response removed", true);
+                continue;
+            };
+            let (Ok(old_response), Ok(new_response)) = (old_response.resolve(self.old), new_response.resolve(self.new)) else {
+                continue;
+            };
+            for header in old_response.headers.keys() {
+                if !new_response.headers.keys().any(|h| h.eq_ignore_ascii_case(header)) {
+                    self.push(ChangeKind::Removed, &format!("{} header {}", location, header), "response header removed", true);
+                }
+            }
+            for header in new_response.headers.keys() {
+                if !old_response.headers.keys().any(|h| h.eq_ignore_ascii_case(header)) {
+                    self.push(ChangeKind::Added, &format!("{} header {}", location, header), "response header added", false);
+                }
+            }
+            self.content(&location, &old_response.content, &new_response.content, Direction::Response);
+        }
+        for status in new.responses.responses.keys().filter(|s| !old.responses.responses.contains_key(*s)) {
+            self.push(ChangeKind::Added, &format!("{} response {}", name, status), "response added", false);
+        }
+    }
+
+    fn content(
+        &mut self,
+        location: &str,
+        old: &indexmap::IndexMap<String, oa::MediaType>,
+        new: &indexmap::IndexMap<String, oa::MediaType>,
+        direction: Direction,
+    ) {
+        for (mime, old_media) in old {
+            let Some(new_media) = new.get(mime) else {
+                self.push(ChangeKind::Removed, location, format!("media type {} removed", mime), true);
+                continue;
+            };
+            if let (Some(old_schema), Some(new_schema)) = (&old_media.schema, &new_media.schema) {
+                self.schema(&format!("{} body", location), old_schema, new_schema, direction);
+            }
+        }
+        for mime in new.keys().filter(|m| !old.contains_key(*m)) {
+            self.push(ChangeKind::Added, location, format!("media type {} added", mime), false);
+        }
+    }
+
+    fn schema(&mut self, location: &str, old: &RefOr<oa::Schema>, new: &RefOr<oa::Schema>, direction: Direction) {
+        let pair = old.as_ref_str().zip(new.as_ref_str()).map(|(o, n)| (o.to_string(), n.to_string()));
+        if let Some(pair) = &pair {
+            if self.refs.contains(pair) {
+                return;
+            }
+            self.refs.push(pair.clone());
+        }
+        match (resolve_schema(self.old, old), resolve_schema(self.new, new)) {
+            (Some(old), Some(new)) => self.resolved_schema(location, old, new, direction),
+            (old_schema, new_schema) => {
+                let missing = if old_schema.is_none() { old } else { new };
+                let description = format!("missing reference {}", missing.as_ref_str().unwrap_or_default());
+                self.push(ChangeKind::Changed, location, description, new_schema.is_none());
+            }
+        }
+        if pair.is_some() {
+            self.refs.pop();
+        }
+    }
+
+    fn resolved_schema(&mut self, location: &str, old: &oa::Schema, new: &oa::Schema, direction: Direction) {
+        let (old_type, new_type) = (type_name(old), type_name(new));
+        if old_type != new_type {
+            let description = format!("type changed from {} to {}", old_type, new_type);
+            self.push(ChangeKind::Changed, location, description, true);
+            return;
+        }
+        let removed = string_enum(old).iter().filter(|v| !string_enum(new).contains(v)).count();
+        let added = string_enum(new).iter().filter(|v| !string_enum(old).contains(v)).count();
+        // Only closing an open enum or removing values narrows what requests may send; responses
+        // break clients that switch over the values when new ones appear.
+        if removed > 0 && !string_enum(new).is_empty() {
+            self.push(ChangeKind::Changed, location, format!("{} enum values removed", removed), direction == Direction::Request);
+        }
+        if added > 0 && !string_enum(old).is_empty() {
+            self.push(ChangeKind::Changed, location, format!("{} enum values added", added), direction == Direction::Response);
+        }
+        use oa::SchemaKind::Type as T;
+        match (&old.kind, &new.kind) {
+            (T(oa::Type::Object(old)), T(oa::Type::Object(new))) => {
+                for (name, old_prop) in &old.properties {
+                    let location = format!("{}.{}", location, name);
+                    let Some(new_prop) = new.properties.get(name) else {
+                        self.push(ChangeKind::Removed, &location, "property removed", direction == Direction::Response);
+                        continue;
+                    };
+                    let (was_required, is_required) = (old.required.contains(name), new.required.contains(name));
+                    if was_required != is_required {
+                        let (description, breaking) = match (is_required, direction) {
+                            (true, Direction::Request) => ("property became required", true),
+                            (false, Direction::Response) => ("property became optional", true),
+                            (true, Direction::Response) => ("property became required", false),
+                            (false, Direction::Request) => ("property became optional", false),
+                        };
+                        self.push(ChangeKind::Changed, &location, description, breaking);
+                    }
+                    self.schema(&location, old_prop, new_prop, direction);
+                }
+                for name in new.properties.keys().filter(|n| !old.properties.contains_key(*n)) {
+                    let required = new.required.contains(name);
+                    let breaking = required && direction == Direction::Request;
+                    let description = if required { "required property added" } else { "optional property added" };
+                    self.push(ChangeKind::Added, &format!("{}.{}", location, name), description, breaking);
+                }
+            }
+            (T(oa::Type::Array(old)), T(oa::Type::Array(new))) => {
+                if let (Some(old_items), Some(new_items)) = (&old.items, &new.items) {
+                    self.schema(&format!("{}[]", location), old_items, new_items, direction);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Render the changes as Markdown, breaking changes first.
+pub fn to_markdown(changes: &[Change]) -> String {
+    let mut out = String::from("# API changes\n");
+    if changes.is_empty() {
+        out.push_str("\nNo changes.\n");
+        return out;
+    }
+    for (title, breaking) in [("Breaking changes", true), ("Non-breaking changes", false)] {
+        let section = changes.iter().filter(|c| c.breaking == breaking).collect::<Vec<_>>();
+        if section.is_empty() {
+            continue;
+        }
+        writeln!(out, "\n## {}\n", title).unwrap();
+        for change in section {
+            writeln!(out, "- `{}`: {}", change.location, change.description).unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_specs() {
+        let old: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {
+                "/users": {"get": {
+                    "parameters": [{"name": "page", "in": "query", "schema": {"type": "integer"}}],
+                    "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}}},
+                }},
+                "/teams": {"get": {"responses": {}}},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "properties": {"id": {"type": "integer"}, "name": {"type": "string"}}},
+            }},
+        }))
+        .unwrap();
+        let new: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "2"},
+            "paths": {
+                "/users": {"get": {
+                    "parameters": [
+                        {"name": "page", "in": "query", "schema": {"type": "string"}},
+                        {"name": "org", "in": "query", "required": true, "schema": {"type": "string"}},
+                    ],
+                    "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}}},
+                }},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "properties": {"id": {"type": "integer"}, "email": {"type": "string"}}},
+            }},
+        }))
+        .unwrap();
+        let changes = diff_specs(&old, &new)
+            .into_iter()
+            .map(|c| (c.location, c.description, c.breaking))
+            .collect::<Vec<_>>();
+        let body = "GET /users response 200 body";
+        assert_eq!(changes, vec![
+            ("GET /users query parameter page".to_string(), "type changed from integer to string".to_string(), true),
+            ("GET /users query parameter org".to_string(), "required parameter added".to_string(), true),
+            (format!("{}.name", body), "property removed".to_string(), true),
+            (format!("{}.email", body), "optional property added".to_string(), false),
+            ("GET /teams".to_string(), "operation removed".to_string(), true),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_shared_schema() {
+        let spec = |user: serde_json::Value| -> oa::OpenAPI {
+            let content = json!({"application/json": {"schema": {"$ref": "#/components/schemas/User"}}});
+            serde_json::from_value(json!({
+                "openapi": "3.0.3",
+                "info": {"title": "Test", "version": "1"},
+                "paths": {"/users": {
+                    "post": {"requestBody": {"content": content}, "responses": {}},
+                    "get": {"responses": {"200": {"description": "", "content": content}}},
+                }},
+                "components": {"schemas": {
+                    "User": user,
+                    "Node": {"type": "object", "properties": {"parent": {"$ref": "#/components/schemas/Node"}}},
+                }},
+            }))
+            .unwrap()
+        };
+        let old = spec(json!({"type": "object", "properties": {"name": {"type": "string"}, "parent": {"$ref": "#/components/schemas/Node"}}}));
+        let new = spec(json!({"type": "object", "properties": {"parent": {"$ref": "#/components/schemas/Node"}}}));
+        let changes = diff_specs(&old, &new)
+            .into_iter()
+            .map(|c| (c.location, c.breaking))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![
+            ("GET /users response 200 body.name".to_string(), true),
+            ("POST /users request body body.name".to_string(), false),
+        ]);
+
+        let dangling = spec(json!({"type": "object", "properties": {"parent": {"$ref": "#/components/schemas/Missing"}}}));
+        let changes = diff_specs(&new, &dangling)
+            .into_iter()
+            .map(|c| (c.location, c.description, c.breaking))
+            .collect::<Vec<_>>();
+        assert_eq!(changes[0], (
+            "GET /users response 200 body.parent".to_string(),
+            "missing reference #/components/schemas/Missing".to_string(),
+            true,
+        ));
+    }
+}
@@ -0,0 +1,328 @@
+use super::schema::resolve_schema;
+use indexmap::IndexMap;
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use serde_json::Value;
+
+/// Arrays in examples are truncated to this many items.
+const MAX_EXAMPLE_ITEMS: usize = 3;
+/// At most this many distinct examples are kept per parameter or media type.
+const MAX_EXAMPLES: usize = 5;
+
+/// Substrings of keys (compared case-insensitively) whose values are never copied into examples.
+const SENSITIVE_KEYS: [&str; 11] = [
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "authorization",
+    "api_key",
+    "apikey",
+    "api-key",
+    "session",
+    "cookie",
+    "credential",
+];
+
+pub const REDACTED: &str = "REDACTED";
+
+/// Decides which captured values are too sensitive to end up in the generated docs.
+/// Keys are matched case-insensitively against a built-in list plus any extra keys.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    extra_keys: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(extra_keys: &[String]) -> Self {
+        Self {
+            extra_keys: extra_keys.iter().map(|k| k.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        SENSITIVE_KEYS.iter().any(|k| key.contains(k))
+            || self.extra_keys.iter().any(|k| key.contains(k.as_str()))
+    }
+
+    /// Turn a captured value into an example: sensitive keys are redacted and arrays truncated.
+    pub fn example(&self, value: &Value) -> Value {
+        match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .take(MAX_EXAMPLE_ITEMS)
+                    .map(|v| self.example(v))
+                    .collect(),
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| {
+                        let v = if self.is_sensitive(k) {
+                            Value::from(REDACTED)
+                        } else {
+                            self.example(v)
+                        };
+                        (k.clone(), v)
+                    })
+                    .collect(),
+            ),
+            v => v.clone(),
+        }
+    }
+
+    /// Like [Redactor::example], for a value that is itself named `key` (e.g. a query parameter).
+    pub fn example_for(&self, key: &str, value: &Value) -> Value {
+        if self.is_sensitive(key) {
+            Value::from(REDACTED)
+        } else {
+            self.example(value)
+        }
+    }
+}
+
+/// Add `value` to `examples` as `example{n}`, unless an identical example is already present.
+pub fn add_example(
+    examples: &mut IndexMap<String, RefOr<oa::Example>>,
+    value: Value,
+    summary: Option<String>,
+) {
+    let exists = examples
+        .values()
+        .any(|e| e.as_item().and_then(|e| e.value.as_ref()) == Some(&value));
+    if exists || examples.len() >= MAX_EXAMPLES {
+        return;
+    }
+    let name = format!("example{}", examples.len() + 1);
+    examples.insert(
+        name,
+        RefOr::Item(oa::Example {
+            summary,
+            value: Some(value),
+            ..oa::Example::default()
+        }),
+    );
+}
+
+/// An example for a media type: its own `example` or first `examples` entry if it has one,
+/// otherwise one built from its schema with [example_from_schema].
+pub fn media_example(spec: &oa::OpenAPI, media: &oa::MediaType) -> Value {
+    media
+        .example
+        .clone()
+        .or_else(|| media.examples.values().find_map(|e| e.as_item()?.value.clone()))
+        .or_else(|| Some(example_from_schema(spec, media.schema.as_ref()?, &mut Vec::new())))
+        .unwrap_or(Value::Null)
+}
+
+/// Build a value that satisfies `schema`, preferring the schema's own examples. `refs` holds the
+/// references being expanded; a recursive or missing reference yields `null`.
+pub fn example_from_schema(spec: &oa::OpenAPI, schema: &RefOr<oa::Schema>, refs: &mut Vec<String>) -> Value {
+    let Some(resolved) = resolve_schema(spec, schema) else {
+        return Value::Null;
+    };
+    if let Some(reference) = schema.as_ref_str() {
+        if refs.iter().any(|r| r == reference) {
+            return Value::Null;
+        }
+        refs.push(reference.to_string());
+    }
+    let value = match (&resolved.data.example, &resolved.kind) {
+        (Some(example), _) => example.clone(),
+        (None, oa::SchemaKind::Type(oa::Type::String(s))) => match (s.enumeration.first(), &s.format) {
+            (Some(value), _) => Value::from(value.as_str()),
+            (None, oa::VariantOrUnknownOrEmpty::Item(oa::StringFormat::Date)) => Value::from("2023-08-01"),
+            (None, oa::VariantOrUnknownOrEmpty::Item(oa::StringFormat::DateTime)) => Value::from("2023-08-01T12:00:00Z"),
+            (None, oa::VariantOrUnknownOrEmpty::Unknown(f)) if f == "uuid" => Value::from("00000000-0000-0000-0000-000000000000"),
+            (None, oa::VariantOrUnknownOrEmpty::Unknown(f)) if f == "email" => Value::from("user@example.com"),
+            (None, oa::VariantOrUnknownOrEmpty::Unknown(f)) if f == "uri" => Value::from("https://example.com"),
+            _ => Value::from("string"),
+        },
+        (None, oa::SchemaKind::Type(oa::Type::Integer(_))) => Value::from(0),
+        (None, oa::SchemaKind::Type(oa::Type::Number(_))) => Value::from(0.0),
+        (None, oa::SchemaKind::Type(oa::Type::Boolean {})) => Value::from(true),
+        (None, oa::SchemaKind::Type(oa::Type::Array(a))) => Value::Array(
+            a.items
+                .iter()
+                .map(|items| example_from_schema(spec, items, refs))
+                // A recursive item schema stops the tree with an empty array rather than `[null]`.
+                .filter(|v| !v.is_null())
+                .collect(),
+        ),
+        (None, oa::SchemaKind::Type(oa::Type::Object(o))) => Value::Object(
+            o.properties
+                .iter()
+                .map(|(name, property)| (name.clone(), example_from_schema(spec, property, refs)))
+                .collect(),
+        ),
+        (None, oa::SchemaKind::AllOf { all_of }) => {
+            let mut merged = serde_json::Map::new();
+            for schema in all_of {
+                if let Value::Object(map) = example_from_schema(spec, schema, refs) {
+                    merged.extend(map);
+                }
+            }
+            Value::Object(merged)
+        }
+        (None, oa::SchemaKind::OneOf { one_of: schemas } | oa::SchemaKind::AnyOf { any_of: schemas }) => schemas
+            .first()
+            .map(|schema| example_from_schema(spec, schema, refs))
+            .unwrap_or(Value::Null),
+        (None, oa::SchemaKind::Not { .. } | oa::SchemaKind::Any(_)) => Value::Null,
+    };
+    if schema.as_ref_str().is_some() {
+        refs.pop();
+    }
+    value
+}
+
+/// Remove schema-level examples of sensitive properties, and of everything nested in them, from
+/// every component, or every schema-level example if `redactor` is `None` (examples are disabled).
+pub fn redact_schema_examples(components: &mut oa::Components, redactor: Option<&Redactor>) {
+    let mut sensitive = Vec::new();
+    for (_, schema) in components.schemas.iter_mut() {
+        if let RefOr::Item(schema) = schema {
+            redact_schema(schema, redactor, &mut sensitive);
+        }
+    }
+    // Components referenced from a sensitive property lose their examples too, as do the
+    // components they reference in turn.
+    let mut redacted = Vec::new();
+    while let Some(name) = sensitive.pop() {
+        if redacted.contains(&name) {
+            continue;
+        }
+        if let Some(RefOr::Item(schema)) = components.schemas.get_mut(&name) {
+            redact_schema(schema, None, &mut sensitive);
+        }
+        redacted.push(name);
+    }
+}
+
+/// Remove every example in `schema`, including those of nested schemas. Referenced components
+/// are left alone.
+pub fn strip_examples(schema: &mut oa::Schema) {
+    redact_schema(schema, None, &mut Vec::new());
+}
+
+/// Redact `schema` in place. With no `redactor`, every example in it is removed, and the names
+/// of the components it references are added to `sensitive`.
+fn redact_schema(schema: &mut oa::Schema, redactor: Option<&Redactor>, sensitive: &mut Vec<String>) {
+    if redactor.is_none() {
+        schema.data.example = None;
+    }
+    match &mut schema.kind {
+        oa::SchemaKind::Type(oa::Type::Object(o)) => {
+            for (key, prop) in o.properties.iter_mut() {
+                // Everything under a sensitive key is redacted, not just the key's own example.
+                let redactor = redactor.filter(|r| !r.is_sensitive(key));
+                redact_child(prop, redactor, sensitive);
+            }
+            if let Some(oa::AdditionalProperties::Schema(s)) = &mut o.additional_properties {
+                redact_child(s, redactor, sensitive);
+            }
+        }
+        oa::SchemaKind::Type(oa::Type::Array(oa::ArrayType { items: Some(items), .. })) => {
+            redact_child(items, redactor, sensitive);
+        }
+        oa::SchemaKind::AllOf { all_of: schemas }
+        | oa::SchemaKind::OneOf { one_of: schemas }
+        | oa::SchemaKind::AnyOf { any_of: schemas } => {
+            for s in schemas.iter_mut() {
+                redact_child(s, redactor, sensitive);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn redact_child(schema: &mut RefOr<oa::Schema>, redactor: Option<&Redactor>, sensitive: &mut Vec<String>) {
+    match schema {
+        RefOr::Item(schema) => redact_schema(schema, redactor, sensitive),
+        RefOr::Reference { reference } if redactor.is_none() => {
+            sensitive.push(reference.rsplit('/').next().unwrap_or(reference).to_string());
+        }
+        RefOr::Reference { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_example_redacts_and_truncates() {
+        let redactor = Redactor::new(&["ssn".to_string()]);
+        let value = json!({
+            "user": {"name": "Ann", "SSN": "123-45-6789", "accessToken": "abc"},
+            "ids": [1, 2, 3, 4, 5],
+        });
+        assert_eq!(
+            redactor.example(&value),
+            json!({
+                "user": {"name": "Ann", "SSN": REDACTED, "accessToken": REDACTED},
+                "ids": [1, 2, 3],
+            })
+        );
+    }
+
+    #[test]
+    fn test_add_example_skips_duplicates() {
+        let mut examples = IndexMap::new();
+        add_example(&mut examples, json!({"id": 1}), None);
+        add_example(&mut examples, json!({"id": 1}), None);
+        add_example(&mut examples, json!({"id": 2}), None);
+        assert_eq!(examples.keys().collect::<Vec<_>>(), vec!["example1", "example2"]);
+    }
+
+    #[test]
+    fn test_redact_schema_examples() {
+        let mut components: oa::Components = serde_json::from_value(json!({"schemas": {
+            "Account": {"type": "object", "properties": {
+                "name": {"type": "string", "example": "Ann"},
+                "credentials": {"type": "object", "properties": {
+                    "value": {"type": "string", "example": "hunter2"},
+                }},
+                "session": {"$ref": "#/components/schemas/Session"},
+            }},
+            "Session": {"type": "object", "properties": {
+                "id": {"type": "string", "example": "abc"},
+                "device": {"$ref": "#/components/schemas/Device"},
+            }},
+            "Device": {"type": "object", "properties": {"ip": {"type": "string", "example": "10.0.0.1"}}},
+        }}))
+        .unwrap();
+        redact_schema_examples(&mut components, Some(&Redactor::default()));
+        let examples = serde_json::to_string(&components).unwrap();
+        assert!(examples.contains("Ann"));
+        for value in ["hunter2", "abc", "10.0.0.1"] {
+            assert!(!examples.contains(value), "{} was not redacted", value);
+        }
+    }
+
+    #[test]
+    fn test_example_from_schema() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {},
+            "components": {"schemas": {
+                "Node": {"type": "object", "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "status": {"type": "string", "enum": ["active", "archived"]},
+                    "count": {"type": "integer", "example": 7},
+                    "children": {"type": "array", "items": {"$ref": "#/components/schemas/Node"}},
+                    "owner": {"$ref": "#/components/schemas/Missing"},
+                }},
+            }},
+        }))
+        .unwrap();
+        let schema = RefOr::schema_ref("Node");
+        assert_eq!(
+            example_from_schema(&spec, &schema, &mut Vec::new()),
+            json!({"id": "00000000-0000-0000-0000-000000000000", "status": "active", "count": 7, "children": [], "owner": null})
+        );
+    }
+}
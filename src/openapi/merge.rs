@@ -0,0 +1,219 @@
+use crate::openapi::schema;
+use crate::openapi::update;
+use clap::ValueEnum;
+use openapiv3 as oa;
+use openapiv3::{RefOr, RefOrMap};
+use std::fmt;
+
+/// What to do when both specs define the same operation or component differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keep the definition from the earlier spec.
+    PreferLeft,
+    /// Take the definition from the later spec.
+    PreferRight,
+    /// Combine both: operations get the union of their parameters and responses, and schemas the
+    /// union of their properties. Other components keep the earlier definition.
+    Union,
+    /// Keep the earlier definition, and fail once every conflict has been reported.
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The conflicting operation (`GET /users`) or component (`schema User`).
+    pub location: String,
+    pub resolution: &'static str,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "conflicting {}, {}", self.location, self.resolution)
+    }
+}
+
+const KEPT_LEFT: &str = "kept left";
+const TOOK_RIGHT: &str = "took right";
+const MERGED: &str = "merged";
+const NOT_MERGEABLE: &str = "kept left (cannot be merged)";
+
+/// Merge `right` into `left`, resolving every operation or component that both define
+/// differently according to `policy`. Returns the conflicts and how each was resolved.
+pub fn merge_specs(left: &mut oa::OpenAPI, right: oa::OpenAPI, policy: ConflictPolicy) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for server in right.servers {
+        if !left.servers.iter().any(|s| s.url == server.url) {
+            left.servers.push(server);
+        }
+    }
+    for (path, item) in right.paths.paths {
+        let Some(item) = item.into_item() else {
+            continue;
+        };
+        let Some(existing) = left.paths.paths.get_mut(&path) else {
+            left.paths.paths.insert(path, RefOr::Item(item));
+            continue;
+        };
+        let Some(existing) = existing.as_mut() else {
+            conflicts.push(Conflict {
+                location: format!("path {}", path),
+                resolution: NOT_MERGEABLE,
+            });
+            continue;
+        };
+        for (method, operation) in update::into_operations(item) {
+            let location = format!("{} {}", method.to_uppercase(), path);
+            let slot = update::operation_mut(existing, method);
+            let Some(current) = slot.as_mut() else {
+                *slot = Some(operation);
+                continue;
+            };
+            if *current == operation {
+                continue;
+            }
+            let resolution = match policy {
+                ConflictPolicy::PreferLeft | ConflictPolicy::Fail => KEPT_LEFT,
+                ConflictPolicy::PreferRight => {
+                    *current = operation;
+                    TOOK_RIGHT
+                }
+                ConflictPolicy::Union => {
+                    update::update_operation(&location, current, operation, &left.components, &left.security, &mut Vec::new());
+                    MERGED
+                }
+            };
+            conflicts.push(Conflict { location, resolution });
+        }
+    }
+    let components = right.components;
+    merge_map("schema", &mut left.components.schemas, components.schemas, policy, &mut conflicts, |l, r| {
+        if !can_union(l, &r) {
+            return false;
+        }
+        schema::merge_schema(l, r);
+        true
+    });
+    merge_map("response", &mut left.components.responses, components.responses, policy, &mut conflicts, cannot_merge);
+    merge_map("parameter", &mut left.components.parameters, components.parameters, policy, &mut conflicts, cannot_merge);
+    merge_map("example", &mut left.components.examples, components.examples, policy, &mut conflicts, cannot_merge);
+    merge_map("request body", &mut left.components.request_bodies, components.request_bodies, policy, &mut conflicts, cannot_merge);
+    merge_map("header", &mut left.components.headers, components.headers, policy, &mut conflicts, cannot_merge);
+    merge_map("security scheme", &mut left.components.security_schemes, components.security_schemes, policy, &mut conflicts, cannot_merge);
+    merge_map("link", &mut left.components.links, components.links, policy, &mut conflicts, cannot_merge);
+    merge_map("callback", &mut left.components.callbacks, components.callbacks, policy, &mut conflicts, cannot_merge);
+    for requirement in right.security {
+        if !left.security.contains(&requirement) {
+            left.security.push(requirement);
+        }
+    }
+    for tag in right.tags {
+        if !left.tags.iter().any(|t| t.name == tag.name) {
+            left.tags.push(tag);
+        }
+    }
+    conflicts
+}
+
+/// Unlike [schema::is_compatible], objects only need to agree on the properties they share.
+fn can_union(a: &oa::Schema, b: &oa::Schema) -> bool {
+    use oa::SchemaKind::Type as T;
+    match (&a.kind, &b.kind) {
+        (T(oa::Type::Object(a)), T(oa::Type::Object(b))) => a.properties.iter().all(|(key, a)| {
+            match (a, b.properties.get(key)) {
+                (RefOr::Item(a), Some(RefOr::Item(b))) => can_union(a, b),
+                (a, Some(b)) => a == b,
+                (_, None) => true,
+            }
+        }),
+        _ => schema::is_compatible(a, b),
+    }
+}
+
+fn cannot_merge<T>(_: &mut T, _: T) -> bool {
+    false
+}
+
+/// Merge a map of components. `union` merges two inline definitions, returning false if they
+/// cannot be merged.
+fn merge_map<T: PartialEq>(
+    kind: &str,
+    left: &mut RefOrMap<T>,
+    right: RefOrMap<T>,
+    policy: ConflictPolicy,
+    conflicts: &mut Vec<Conflict>,
+    union: impl Fn(&mut T, T) -> bool,
+) {
+    for (name, value) in right {
+        let Some(current) = left.get_mut(&name) else {
+            left.insert(name, value);
+            continue;
+        };
+        if *current == value {
+            continue;
+        }
+        let resolution = match (policy, current, value) {
+            (ConflictPolicy::PreferLeft | ConflictPolicy::Fail, _, _) => KEPT_LEFT,
+            (ConflictPolicy::PreferRight, current, value) => {
+                *current = value;
+                TOOK_RIGHT
+            }
+            (ConflictPolicy::Union, RefOr::Item(current), RefOr::Item(value)) => {
+                if union(current, value) {
+                    MERGED
+                } else {
+                    NOT_MERGEABLE
+                }
+            }
+            (ConflictPolicy::Union, _, _) => NOT_MERGEABLE,
+        };
+        conflicts.push(Conflict {
+            location: format!("{} {}", kind, name),
+            resolution,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(email_type: &str, extra: &str) -> oa::OpenAPI {
+        serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {
+                "/users": {"get": {
+                    "parameters": [{"name": extra, "in": "query", "schema": {"type": "string"}}],
+                    "responses": {},
+                }},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "properties": {"email": {"type": email_type}, extra: {"type": "string"}}},
+            }},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_specs_policies() {
+        let mut left = spec("string", "page");
+        let conflicts = merge_specs(&mut left, spec("string", "sort"), ConflictPolicy::Union);
+        assert_eq!(
+            conflicts.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["conflicting GET /users, merged", "conflicting schema User, merged"]
+        );
+        let op = left.paths.paths["/users"].as_item().unwrap().get.as_ref().unwrap();
+        assert_eq!(op.parameters.len(), 2);
+        assert_eq!(left.components.schemas.get2("User").unwrap().properties().len(), 3);
+
+        let mut left = spec("string", "page");
+        let conflicts = merge_specs(&mut left, spec("integer", "page"), ConflictPolicy::Union);
+        assert_eq!(conflicts[0].resolution, NOT_MERGEABLE);
+
+        let mut left = spec("string", "page");
+        merge_specs(&mut left, spec("integer", "page"), ConflictPolicy::PreferRight);
+        let email = left.components.schemas.get2("User").unwrap().properties().get2("email").unwrap();
+        assert!(matches!(email.kind, oa::SchemaKind::Type(oa::Type::Integer(_))));
+    }
+}
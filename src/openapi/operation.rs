@@ -1,11 +1,29 @@
 use crate::http::{ParameterType, RequestBody, RequestResponse};
 use crate::openapi;
+use crate::openapi::example::{self, Redactor};
 use crate::openapi::parameter;
-use indexmap::indexmap;
+use crate::openapi::response;
+use crate::openapi::security;
+use indexmap::{indexmap, IndexMap};
 use itertools::Itertools;
 use openapiv3 as oa;
 use openapiv3::{RefOr, ReferenceOr};
 use std::str::FromStr;
+use tracing::debug;
+
+/// Settings for how operations are created from the captured requests.
+#[derive(Debug, Default)]
+pub struct OperationOptions {
+    /// Redacts captured values before they are used as examples. `None` disables examples.
+    pub redactor: Option<Redactor>,
+    /// Headers that are not documented on requests or responses, in addition to
+    /// [crate::http::ignore_header].
+    pub ignore_headers: Vec<String>,
+    /// Name of the session cookie. If `None`, session cookies are recognized by name.
+    pub session_cookie: Option<String>,
+    /// Security scheme that issued the bearer tokens (see [security::bearer_scheme]).
+    pub bearer_scheme: Option<String>,
+}
 
 pub fn make_body(body: &Option<RequestBody>) -> Option<RefOr<oa::RequestBody>> {
     let body = body.as_ref()?;
@@ -37,16 +55,20 @@ pub fn make_body(body: &Option<RequestBody>) -> Option<RefOr<oa::RequestBody>> {
     }))
 }
 
-pub fn create_operation(rr: &RequestResponse) -> anyhow::Result<oa::Operation> {
-    let response = rr.response_schema_ref();
-    let mut parameters = rr
-        .request
-        .query
-        .iter()
-        .unique_by(|(key, _)| key)
-        .map(|(key, value)| parameter::create_parameter(key, value))
-        .collect::<anyhow::Result<Vec<_>, anyhow::Error>>()?
+/// Create the operation for a group of requests to the same path and method. Query parameters
+/// are inferred from every request, the last one determines the request body, and every request
+/// contributes examples. Responses are documented per observed status code.
+pub fn create_operation(rrs: &[&RequestResponse], options: &OperationOptions) -> anyhow::Result<oa::Operation> {
+    let rr = *rrs.last().expect("operation needs at least one request");
+    // Credentials are documented by the security schemes instead.
+    let mut parameters = parameter::create_query_parameters(rrs)
         .into_iter()
+        .filter(|p| !security::is_api_key_query(&p.name))
+        .chain(
+            parameter::create_header_parameters(rrs, &options.ignore_headers)
+                .into_iter()
+                .filter(|p| !security::is_api_key_header(&p.name)),
+        )
         .map(|p| ReferenceOr::Item(p))
         .collect::<Vec<_>>();
     for param in &rr.info.path_parameters {
@@ -57,28 +79,148 @@ pub fn create_operation(rr: &RequestResponse) -> anyhow::Result<oa::Operation> {
         p.required = true;
         parameters.push(p.into());
     }
-    let body = make_body(&rr.request.body);
+    let mut body = make_body(&rr.request.body);
+    if let Some(redactor) = &options.redactor {
+        add_examples(rrs, redactor, &mut parameters, &mut body);
+    }
+    // HAR records a status of 0 for requests that never got a response.
+    let responses = rrs
+        .iter()
+        .copied()
+        .filter(|rr| rr.response.status != 0)
+        .into_group_map_by(|rr| rr.response.status)
+        .into_iter()
+        .sorted_by_key(|(status, _)| *status)
+        .map(|(status, rrs)| (oa::StatusCode::Code(status), RefOr::Item(create_response(&rrs, options))))
+        .collect();
     Ok(oa::Operation {
         operation_id: Some(rr.operation_id().to_string()),
         parameters,
         request_body: body,
+        security: security::operation_security(
+            rrs,
+            options.session_cookie.as_deref(),
+            options.bearer_scheme.as_deref(),
+        ),
         responses: oa::Responses {
             default: None,
-            responses: indexmap! {
-                oa::StatusCode::Code(200) => ReferenceOr::Item(response),
-            },
+            responses,
             extensions: Default::default(),
         },
         ..oa::Operation::default()
     })
 }
 
-pub fn create_paths(rrs: &Vec<RequestResponse>, paths: &mut oa::Paths, server: &str) -> anyhow::Result<()> {
+/// Document the responses that share a status code. Successful bodies reference the response
+/// schema; other bodies are not part of the schema, so they are only shown through examples.
+fn create_response(rrs: &[&RequestResponse], options: &OperationOptions) -> oa::Response {
+    let rr = *rrs.last().expect("response needs at least one request");
+    let mut response = if rrs.iter().all(|rr| rr.response.data.is_null()) {
+        oa::Response::default()
+    } else if rr.response.is_success() {
+        rr.response_schema_ref()
+    } else {
+        oa::Response {
+            content: indexmap! {
+                "application/json".to_string() => oa::MediaType::default(),
+            },
+            ..oa::Response::default()
+        }
+    };
+    response.headers = response::create_response_headers(rrs, &options.ignore_headers, options.redactor.as_ref());
+    if let Some(redactor) = &options.redactor {
+        if let Some(media) = response.content.get_mut("application/json") {
+            for rr in rrs.iter().filter(|rr| !rr.response.data.is_null()) {
+                let value = redactor.example(&rr.response.data);
+                example::add_example(&mut media.examples, value, Some(example_summary(rr, redactor)));
+            }
+        }
+    }
+    response
+}
+
+/// The request path and query, with sensitive query values redacted, e.g. `/users?page=2`.
+fn example_summary(rr: &RequestResponse, redactor: &Redactor) -> String {
+    let path = rr.request.url.path();
+    if rr.request.query.is_empty() {
+        return path.to_string();
+    }
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(rr.request.query.iter().map(|(name, value)| {
+            let value = if redactor.is_sensitive(name) { example::REDACTED } else { value.as_str() };
+            (name, value)
+        }))
+        .finish();
+    format!("{}?{}", path, query)
+}
+
+/// Attach the captured values of every request in the group as examples.
+fn add_examples(
+    rrs: &[&RequestResponse],
+    redactor: &Redactor,
+    parameters: &mut [RefOr<oa::Parameter>],
+    body: &mut Option<RefOr<oa::RequestBody>>,
+) {
+    for rr in rrs {
+        for param in parameters.iter_mut().filter_map(RefOr::as_mut) {
+            let value = match param.kind {
+                oa::ParameterKind::Query { .. } => parameter::query_example(rr, param),
+                oa::ParameterKind::Header { .. } => rr
+                    .request
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&param.name))
+                    .map(|(_, value)| parameter::parse_parameter_value(value)),
+                oa::ParameterKind::Path { .. } => rr
+                    .info
+                    .path_parameters
+                    .iter()
+                    .find(|p| p.name == param.name)
+                    .map(|p| parameter::parse_parameter_value(&p.value)),
+                _ => None,
+            };
+            if let Some(value) = value {
+                let value = redactor.example_for(&param.name, &value);
+                example::add_example(&mut param.examples, value, None);
+            }
+        }
+        if let (Some(RefOr::Item(body)), Some(request_body)) = (body.as_mut(), &rr.request.body) {
+            if let Some(media) = body.content.get_mut("application/json") {
+                let value = redactor.example(&request_body.content);
+                example::add_example(&mut media.examples, value, Some(example_summary(rr, redactor)));
+            }
+        }
+    }
+}
+
+pub fn create_paths(
+    rrs: &[RequestResponse],
+    paths: &mut oa::Paths,
+    server: &str,
+    options: &OperationOptions,
+) -> anyhow::Result<()> {
+    let mut groups: IndexMap<(&str, &str), Vec<&RequestResponse>> = IndexMap::new();
     for rr in rrs {
-        let operation = create_operation(rr)?;
-        let method = oa::PathMethod::from_str(rr.info.method.to_uppercase().as_str()).unwrap();
-        println!("{}", &rr.info.path);
-        paths.insert_operation(rr.info.path.clone(), method, operation);
+        groups.entry((rr.path(), rr.method())).or_default().push(rr);
+    }
+    for ((path, method), rrs) in groups {
+        let operation = create_operation(&rrs, options)?;
+        let method = oa::PathMethod::from_str(method.to_uppercase().as_str()).unwrap();
+        debug!(path, method = %method, n = rrs.len(), "Created operation");
+        paths.insert_operation(path.to_string(), method, operation);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_entry;
+    use serde_json::json;
+
+    #[test]
+    fn test_example_summary_redacts_query() {
+        let rr = RequestResponse::from(test_entry("GET", "https://api.example.com/users?page=2&api_key=abc", json!([])));
+        assert_eq!(example_summary(&rr, &Redactor::default()), "/users?page=2&api_key=REDACTED");
+    }
+}
@@ -1,34 +1,483 @@
+use crate::http::{Header, Query, RequestResponse};
 use anyhow::Result;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use once_cell::sync::Lazy;
 use openapiv3 as oa;
+use regex::Regex;
+use serde_json::Value;
+use tracing::warn;
+
+/// A string parameter is an enum if it takes at least two and at most this many distinct values...
+const MAX_ENUM_VALUES: usize = 10;
+/// ...and each value was seen on average at least this many times.
+const MIN_ENUM_REPEATS: usize = 2;
+
+/// The type of a single query value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Boolean,
+    Integer,
+    Number,
+    String,
+}
+
+impl ValueType {
+    fn of(value: &str) -> Self {
+        if value.parse::<i64>().is_ok() {
+            ValueType::Integer
+        } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+            ValueType::Number
+        } else if value == "true" || value == "false" {
+            ValueType::Boolean
+        } else {
+            ValueType::String
+        }
+    }
+
+    /// The narrowest type that can hold both.
+    fn widen(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ValueType::Integer, ValueType::Number) | (ValueType::Number, ValueType::Integer) => ValueType::Number,
+            _ => ValueType::String,
+        }
+    }
+}
 
 /// Examine the key (e.g. "id[]") and attempt parses (e.g. int, float) on the value to infer
 /// an oa::Schema for the parameter.
 pub fn infer_parameter_schema(key: &str, value: &str) -> oa::Schema {
-    if key.ends_with("[]") {
-        let key = &key[..key.len() - 2];
-        let inner_schema = infer_parameter_schema(key, value);
+    infer_parameter_schema_from_values(key, &[value])
+}
+
+/// Like [infer_parameter_schema], but widens the type across every value the parameter was seen
+/// with (e.g. `5` and `abc` make a string), and detects enums and string formats from the full set.
+pub fn infer_parameter_schema_from_values(key: &str, values: &[&str]) -> oa::Schema {
+    if let Some(key) = key.strip_suffix("[]") {
+        let inner_schema = infer_parameter_schema_from_values(key, values);
         return oa::Schema::new_array(inner_schema);
     }
-    let schema = if value.parse::<i32>().is_ok() {
-        oa::Schema::new_integer()
-    } else if value.parse::<f32>().is_ok() {
-        oa::Schema::new_number()
+    let typ = values
+        .iter()
+        .map(|v| ValueType::of(v))
+        .reduce(ValueType::widen)
+        .unwrap_or(ValueType::String);
+    match typ {
+        ValueType::Boolean => oa::Schema::new_bool(),
+        ValueType::Integer => oa::Schema::new_integer(),
+        ValueType::Number => oa::Schema::new_number(),
+        ValueType::String => {
+            let distinct = values.iter().copied().unique().collect::<Vec<_>>();
+            if let Some(format) = detect_format(&distinct) {
+                oa::Schema::new_string().with_format(format)
+            } else if is_enum(&distinct, values.len()) {
+                oa::Schema::new_str_enum(distinct.iter().sorted().map(|v| v.to_string()).collect())
+            } else {
+                oa::Schema::new_string()
+            }
+        }
+    }
+}
+
+/// The string format shared by every value, if any.
+fn detect_format(values: &[&str]) -> Option<&'static str> {
+    static FORMATS: Lazy<Vec<(&'static str, Regex)>> = Lazy::new(|| {
+        [
+            ("date-time", r"^\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?$"),
+            ("date", r"^\d{4}-\d{2}-\d{2}$"),
+            ("uuid", r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"),
+            ("email", r"^[^@\s]+@[^@\s]+\.[^@\s]+$"),
+        ]
+        .into_iter()
+        .map(|(format, re)| (format, Regex::new(re).unwrap()))
+        .collect()
+    });
+    if values.is_empty() {
+        return None;
+    }
+    FORMATS
+        .iter()
+        .find(|(_, re)| values.iter().all(|v| re.is_match(v)))
+        .map(|(format, _)| *format)
+}
+
+fn is_enum(distinct: &[&str], total: usize) -> bool {
+    static IDENTIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_.\-]{0,39}$").unwrap());
+    (2..=MAX_ENUM_VALUES).contains(&distinct.len())
+        && total >= distinct.len() * MIN_ENUM_REPEATS
+        && distinct.iter().all(|v| IDENTIFIER.is_match(v))
+}
+
+/// Parse a query or path value into the JSON value it most likely represents, e.g. for examples.
+pub fn parse_parameter_value(value: &str) -> serde_json::Value {
+    if let Ok(i) = value.parse::<i64>() {
+        i.into()
+    } else if let Some(n) = value.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        n.into()
     } else if value == "true" || value == "false" {
-        oa::Schema::new_bool()
+        (value == "true").into()
     } else {
-        oa::Schema::new_string()
+        value.into()
+    }
+}
+
+/// The value of one query parameter in a single request, after grouping its keys.
+#[derive(Debug)]
+pub(crate) enum QueryValue<'a> {
+    /// `key=a`, possibly a delimited list like `key=a,b`
+    Scalar(&'a str),
+    /// `key[]=a&key[]=b` or `key=a&key=b`
+    List(Vec<&'a str>),
+    /// `key[a]=1&key[b]=2`
+    Object(IndexMap<&'a str, &'a str>),
+}
+
+/// Split `filter[name]` into `("filter", "name")`.
+fn split_deep_object_key(key: &str) -> Option<(&str, &str)> {
+    let (name, rest) = key.split_once('[')?;
+    let property = rest.strip_suffix(']')?;
+    (!name.is_empty() && !property.is_empty()).then_some((name, property))
+}
+
+/// Group the query pairs of a request by parameter name.
+fn group_query(query: &[Query]) -> IndexMap<&str, QueryValue<'_>> {
+    let mut grouped: IndexMap<&str, QueryValue> = IndexMap::new();
+    for (key, value) in query {
+        let value = value.as_str();
+        if let Some(name) = key.strip_suffix("[]") {
+            match grouped.entry(name).or_insert_with(|| QueryValue::List(Vec::new())) {
+                QueryValue::List(items) => items.push(value),
+                QueryValue::Scalar(first) => {
+                    let first = *first;
+                    grouped.insert(name, QueryValue::List(vec![first, value]));
+                }
+                QueryValue::Object(_) => warn!(key, "Query key used as both a list and an object"),
+            }
+        } else if let Some((name, property)) = split_deep_object_key(key) {
+            match grouped.entry(name).or_insert_with(|| QueryValue::Object(IndexMap::new())) {
+                QueryValue::Object(properties) => {
+                    properties.insert(property, value);
+                }
+                _ => warn!(key, "Query key used as both a list and an object"),
+            }
+        } else {
+            match grouped.get_mut(key.as_str()) {
+                None => {
+                    grouped.insert(key, QueryValue::Scalar(value));
+                }
+                Some(QueryValue::Scalar(first)) => {
+                    let first = *first;
+                    grouped.insert(key, QueryValue::List(vec![first, value]));
+                }
+                Some(QueryValue::List(items)) => items.push(value),
+                Some(QueryValue::Object(_)) => warn!(key, "Query key used as both a scalar and an object"),
+            }
+        }
+    }
+    grouped
+}
+
+/// The delimiter (`,` for `form` with `explode: false`, or `|` for `pipeDelimited`) that every
+/// value containing it splits cleanly on, if any value contains one.
+fn detect_delimiter(values: &[&str]) -> Option<char> {
+    [',', '|'].into_iter().find(|d| {
+        values.iter().any(|v| v.contains(*d))
+            && values
+                .iter()
+                .filter(|v| v.contains(*d))
+                .all(|v| v.split(*d).all(|part| !part.is_empty() && !part.contains(char::is_whitespace)))
+    })
+}
+
+/// Everything seen for one query parameter across the requests of an operation.
+#[derive(Debug, Default)]
+struct Observed<'a> {
+    /// Number of requests that included the parameter
+    requests: usize,
+    /// Values of `key=a`
+    scalars: Vec<&'a str>,
+    /// Items of `key[]=a` or repeated keys
+    items: Vec<&'a str>,
+    /// Values of `key[property]=a`, and the number of requests that included each property
+    properties: IndexMap<&'a str, (Vec<&'a str>, usize)>,
+}
+
+impl Observed<'_> {
+    fn into_parameter(self, name: &str, required: bool) -> oa::Parameter {
+        let (schema, style, explode) = if !self.properties.is_empty() {
+            let mut schema = oa::Schema::new_object();
+            for (property, (values, requests)) in &self.properties {
+                schema
+                    .properties_mut()
+                    .insert(*property, infer_parameter_schema_from_values(property, values));
+                if *requests == self.requests {
+                    schema.add_required(property);
+                }
+            }
+            (schema, oa::QueryStyle::DeepObject, Some(true))
+        } else if !self.items.is_empty() {
+            let items = self.items.iter().chain(&self.scalars).copied().collect::<Vec<_>>();
+            let schema = oa::Schema::new_array(infer_parameter_schema_from_values(name, &items));
+            (schema, oa::QueryStyle::Form, Some(true))
+        } else if let Some(delimiter) = detect_delimiter(&self.scalars) {
+            let items = self.scalars.iter().flat_map(|v| v.split(delimiter)).collect::<Vec<_>>();
+            let schema = oa::Schema::new_array(infer_parameter_schema_from_values(name, &items));
+            let style = if delimiter == '|' {
+                oa::QueryStyle::PipeDelimited
+            } else {
+                oa::QueryStyle::Form
+            };
+            (schema, style, Some(false))
+        } else {
+            (infer_parameter_schema_from_values(name, &self.scalars), oa::QueryStyle::Form, None)
+        };
+        let mut p = oa::Parameter::query(name, schema);
+        p.required = required;
+        p.explode = explode;
+        p.kind = oa::ParameterKind::Query {
+            allow_reserved: false,
+            style,
+            allow_empty_value: None,
+        };
+        p
+    }
+}
+
+/// Create the query parameters of an operation from every request made to it. A parameter is
+/// required only if every request included it. `key[]`, repeated keys and delimited values
+/// become arrays, and `key[property]` becomes a `deepObject`.
+pub fn create_query_parameters(rrs: &[&RequestResponse]) -> Vec<oa::Parameter> {
+    let mut seen: IndexMap<&str, Observed> = IndexMap::new();
+    for rr in rrs {
+        for (name, value) in group_query(&rr.request.query) {
+            let observed = seen.entry(name).or_default();
+            observed.requests += 1;
+            match value {
+                QueryValue::Scalar(v) => observed.scalars.push(v),
+                QueryValue::List(items) => observed.items.extend(items),
+                QueryValue::Object(properties) => {
+                    for (property, v) in properties {
+                        let (values, requests) = observed.properties.entry(property).or_default();
+                        values.push(v);
+                        *requests += 1;
+                    }
+                }
+            }
+        }
+    }
+    seen.into_iter()
+        .map(|(name, observed)| {
+            let required = observed.requests == rrs.len();
+            observed.into_parameter(name, required)
+        })
+        .collect()
+}
+
+fn header_parameter(name: String, schema: oa::Schema, required: bool) -> oa::Parameter {
+    oa::Parameter {
+        data: oa::ParameterData {
+            name,
+            description: None,
+            required,
+            deprecated: None,
+            format: oa::ParameterSchemaOrContent::Schema(schema.into()),
+            example: None,
+            examples: Default::default(),
+            explode: None,
+            extensions: Default::default(),
+        },
+        kind: oa::ParameterKind::Header {
+            style: oa::HeaderStyle::Simple,
+        },
+    }
+}
+
+/// Create header parameters for the request headers of an operation (e.g. `X-Tenant-Id`), except
+/// those in `ignore` (compared case-insensitively). Header names are matched case-insensitively
+/// across requests, and a header is required only if every request sent it.
+pub fn create_header_parameters(rrs: &[&RequestResponse], ignore: &[String]) -> Vec<oa::Parameter> {
+    collect_headers(rrs.iter().map(|rr| rr.request.headers.as_slice()), ignore)
+        .into_iter()
+        .map(|(name, values, count)| {
+            let schema = infer_parameter_schema_from_values(&name, &values);
+            header_parameter(name, schema, count == rrs.len())
+        })
+        .collect()
+}
+
+/// Group the headers of several messages by case-insensitive name, except those in `ignore`.
+/// Returns each header's display name, its values, and the number of messages that had it.
+pub(crate) fn collect_headers<'a>(
+    messages: impl IntoIterator<Item = &'a [Header]>,
+    ignore: &[String],
+) -> Vec<(String, Vec<&'a str>, usize)> {
+    let mut seen: IndexMap<String, (String, Vec<&str>, usize)> = IndexMap::new();
+    for headers in messages {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| !ignore.iter().any(|i| i.eq_ignore_ascii_case(name)))
+            .unique_by(|(name, _)| name.to_ascii_lowercase());
+        for (name, value) in headers {
+            let (display, values, count) = seen.entry(name.to_ascii_lowercase()).or_default();
+            // Prefer the original casing from HTTP/1.1 captures over HTTP/2's lowercase.
+            if display.is_empty() || display.chars().all(|c| !c.is_ascii_uppercase()) {
+                *display = name.clone();
+            }
+            values.push(value.as_str());
+            *count += 1;
+        }
+    }
+    seen.into_values().collect()
+}
+
+/// The value of the query parameter `param` in `query`, with its keys grouped as in
+/// [create_query_parameters]: repeated keys, `name[]` and `name[key]`. A delimited value (e.g.
+/// `tags=a,b`) is split into a list if the parameter is an array.
+pub(crate) fn query_value<'a>(spec: Option<&oa::OpenAPI>, query: &'a [Query], param: &oa::Parameter) -> Option<QueryValue<'a>> {
+    let value = group_query(query).shift_remove(param.name.as_str())?;
+    let schema = match param.schema() {
+        Some(oa::ReferenceOr::Item(schema)) => Some(schema),
+        Some(schema) => spec.and_then(|spec| super::schema::resolve_schema(spec, schema)),
+        None => None,
     };
-    schema
+    let is_array = schema.is_some_and(|s| matches!(s.kind, oa::SchemaKind::Type(oa::Type::Array(_))));
+    Some(match value {
+        QueryValue::Scalar(v) if is_array => {
+            let delimiter = match &param.kind {
+                oa::ParameterKind::Query { style: oa::QueryStyle::PipeDelimited, .. } => '|',
+                _ => ',',
+            };
+            QueryValue::List(v.split(delimiter).collect())
+        }
+        value => value,
+    })
 }
 
-fn sanitize_parameter_key(key: &str) -> String {
-    key.replace("[]", "")
+/// The value of the query parameter `param` in `rr`, shaped like the parameter's schema.
+pub fn query_example(rr: &RequestResponse, param: &oa::Parameter) -> Option<Value> {
+    let value = match query_value(None, &rr.request.query, param)? {
+        QueryValue::Scalar(v) => parse_parameter_value(v),
+        QueryValue::List(items) => Value::Array(items.iter().map(|v| parse_parameter_value(v)).collect()),
+        QueryValue::Object(properties) => Value::Object(
+            properties
+                .iter()
+                .map(|(k, v)| (k.to_string(), parse_parameter_value(v)))
+                .collect(),
+        ),
+    };
+    Some(value)
 }
 
-/// query parameters are only strings, so we have to infer the type from the value
-pub fn create_parameter(key: &str, value: &str) -> Result<oa::Parameter> {
-    let schema = infer_parameter_schema(key, value);
-    let name = sanitize_parameter_key(key);
-    let p = oa::Parameter::query(name, schema);
-    Ok(p)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{test_entry, test_entry_with_headers};
+    use serde_json::json;
+
+    #[test]
+    fn test_query_parameters_across_samples() -> Result<()> {
+        let rrs = [
+            "https://example.com/items?page=5&status=open&since=2024-01-01",
+            "https://example.com/items?page=abc&status=closed&since=2024-02-01",
+            "https://example.com/items?status=open&since=2024-03-01",
+            "https://example.com/items?status=open&since=2024-03-02&ratio=1.5",
+        ]
+        .into_iter()
+        .map(|url| RequestResponse::from(test_entry("GET", url, json!([]))))
+        .collect::<Vec<_>>();
+        let rrs = rrs.iter().collect::<Vec<_>>();
+        let params = create_query_parameters(&rrs);
+        let by_name = |name: &str| {
+            let p = params.iter().find(|p| p.name == name).unwrap();
+            (p.required, p.schema().unwrap().as_item().unwrap().kind.clone())
+        };
+
+        let (required, kind) = by_name("page");
+        assert!(!required);
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::String(s)) if s.enumeration.is_empty()));
+
+        let (required, kind) = by_name("status");
+        assert!(required);
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::String(s)) if s.enumeration == vec!["closed", "open"]));
+
+        let (_, kind) = by_name("since");
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::String(s)) if s.format.as_str() == "date"));
+
+        let (required, kind) = by_name("ratio");
+        assert!(!required);
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::Number(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_parameter_styles() -> Result<()> {
+        let rrs = [
+            "https://example.com/items?id=1&id=2&tags=a,b&sort=name|date&filter[name]=x&filter[age]=3",
+            "https://example.com/items?id=3&tags=c&filter[name]=y",
+        ]
+        .into_iter()
+        .map(|url| RequestResponse::from(test_entry("GET", url, json!([]))))
+        .collect::<Vec<_>>();
+        let rrs = rrs.iter().collect::<Vec<_>>();
+        let params = create_query_parameters(&rrs);
+        let style = |name: &str| {
+            let p = params.iter().find(|p| p.name == name).unwrap();
+            let oa::ParameterKind::Query { style, .. } = &p.kind else {
+                panic!("expected query parameter");
+            };
+            (style.clone(), p.explode, p.schema().unwrap().as_item().unwrap().kind.clone())
+        };
+
+        let (s, explode, kind) = style("id");
+        assert_eq!((s, explode), (oa::QueryStyle::Form, Some(true)));
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::Array(_))));
+
+        let (s, explode, kind) = style("tags");
+        assert_eq!((s, explode), (oa::QueryStyle::Form, Some(false)));
+        assert!(matches!(kind, oa::SchemaKind::Type(oa::Type::Array(_))));
+
+        let (s, explode, _) = style("sort");
+        assert_eq!((s, explode), (oa::QueryStyle::PipeDelimited, Some(false)));
+
+        let (s, explode, kind) = style("filter");
+        assert_eq!((s, explode), (oa::QueryStyle::DeepObject, Some(true)));
+        let oa::SchemaKind::Type(oa::Type::Object(filter)) = kind else {
+            panic!("expected object");
+        };
+        assert_eq!(filter.required, vec!["name"]);
+        assert!(matches!(filter.properties.get2("age").unwrap().kind, oa::SchemaKind::Type(oa::Type::Integer(_))));
+
+        let filter = params.iter().find(|p| p.name == "filter").unwrap();
+        assert_eq!(query_example(rrs[0], filter), Some(json!({"name": "x", "age": 3})));
+        let tags = params.iter().find(|p| p.name == "tags").unwrap();
+        assert_eq!(query_example(rrs[0], tags), Some(json!(["a", "b"])));
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_parameters() {
+        let entries = [
+            test_entry_with_headers(
+                "GET",
+                "https://example.com/items",
+                json!([]),
+                &[("x-tenant-id", "acme"), ("Content-Type", "application/json"), ("X-Debug", "1")],
+                &[],
+            ),
+            test_entry_with_headers(
+                "GET",
+                "https://example.com/items",
+                json!([]),
+                &[("X-Tenant-Id", "globex"), ("X-Api-Version", "2")],
+                &[],
+            ),
+        ];
+        let rrs = entries.into_iter().map(RequestResponse::from).collect::<Vec<_>>();
+        let rrs = rrs.iter().collect::<Vec<_>>();
+        let params = create_header_parameters(&rrs, &["x-debug".to_string()]);
+        let names = params.iter().map(|p| (p.name.as_str(), p.required)).collect::<Vec<_>>();
+        assert_eq!(names, vec![("X-Tenant-Id", true), ("X-Api-Version", false)]);
+        assert!(matches!(params[0].kind, oa::ParameterKind::Header { .. }));
+    }
 }
@@ -1,9 +1,13 @@
 use crate::http::RequestResponse;
 use crate::openapi;
+use crate::openapi::example::Redactor;
+use crate::openapi::parameter;
 use anyhow::Result;
+use indexmap::IndexMap;
 use openapiv3 as oa;
+use openapiv3::RefOr;
 use std::collections::HashSet;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 /// Root function that takes a response and attaches its data to the OpenAPI object.
 pub fn create_schema_for_responses(
@@ -15,6 +19,10 @@ pub fn create_schema_for_responses(
         seen.insert(rr.request.url.path().to_string());
         // .unique_by(|rr: &RequestResponse| rr.request.url.path().to_string())
 
+        if !rr.response.is_success() {
+            debug!(url = rr.request.url.as_str(), status = rr.response.status, "Skipping schema for unsuccessful response");
+            continue;
+        }
         info!(url = rr.request.url.as_str(), "Analyzing req/res");
         if let Err(e) = openapi::add_response_schemas(components, &rr) {
             warn!(url=rr.request.url.as_str(), err=?e, "Error adding schemas for url");
@@ -24,10 +32,39 @@ pub fn create_schema_for_responses(
     Ok(())
 }
 
+/// Create the documented headers for a group of responses with the same status (e.g. `ETag`,
+/// `X-RateLimit-Remaining`), except those in `ignore`. A header is required only if every
+/// response had it, and with a redactor its first value becomes the example.
+pub fn create_response_headers(
+    rrs: &[&RequestResponse],
+    ignore: &[String],
+    redactor: Option<&Redactor>,
+) -> IndexMap<String, RefOr<oa::Header>> {
+    parameter::collect_headers(rrs.iter().map(|rr| rr.response.headers.as_slice()), ignore)
+        .into_iter()
+        .map(|(name, values, count)| {
+            let schema = parameter::infer_parameter_schema_from_values(&name, &values);
+            let example = redactor.map(|r| r.example_for(&name, &parameter::parse_parameter_value(values[0])));
+            let header = oa::Header {
+                description: None,
+                style: oa::HeaderStyle::Simple,
+                required: count == rrs.len(),
+                deprecated: None,
+                format: oa::ParameterSchemaOrContent::Schema(schema.into()),
+                example,
+                examples: Default::default(),
+                extensions: Default::default(),
+            };
+            (name, RefOr::Item(header))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::http::read_har;
+    use crate::http::{read_har, test_entry_with_headers};
+    use serde_json::json;
 
     #[test]
     fn test_into_rr_activities_list() -> Result<()> {
@@ -59,4 +96,39 @@ mod test {
         ));
         Ok(())
     }
+
+    #[test]
+    fn test_response_headers() {
+        let mut entries = [
+            test_entry_with_headers(
+                "POST",
+                "https://example.com/items",
+                json!({"id": 1}),
+                &[],
+                &[("Location", "/items/1"), ("X-RateLimit-Remaining", "99"), ("Vary", "Accept")],
+            ),
+            test_entry_with_headers(
+                "POST",
+                "https://example.com/items",
+                json!({"id": 2}),
+                &[],
+                &[("location", "/items/2"), ("X-RateLimit-Remaining", "98")],
+            ),
+        ];
+        for entry in entries.iter_mut() {
+            entry.response.status = 201;
+        }
+        let rrs = entries.into_iter().map(RequestResponse::from).collect::<Vec<_>>();
+        assert_eq!(rrs[0].response.status, 201);
+        let rrs = rrs.iter().collect::<Vec<_>>();
+        let headers = create_response_headers(&rrs, &[], Some(&Redactor::default()));
+        assert_eq!(headers.keys().collect::<Vec<_>>(), vec!["Location", "X-RateLimit-Remaining"]);
+        let remaining = headers["X-RateLimit-Remaining"].as_item().unwrap();
+        assert!(remaining.required);
+        assert_eq!(remaining.example, Some(json!(99)));
+        let oa::ParameterSchemaOrContent::Schema(RefOr::Item(schema)) = &remaining.format else {
+            panic!("expected inline schema");
+        };
+        assert!(matches!(schema.kind, oa::SchemaKind::Type(oa::Type::Integer(_))));
+    }
 }
@@ -0,0 +1,385 @@
+use super::example::strip_examples;
+use super::Lu;
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use tracing::{debug, info, warn};
+
+/// Add `schema` to `components.schemas` and return the name it was registered under.
+///
+/// If `name` is already taken, the two schemas are compared structurally. Identical schemas
+/// reuse the existing component, compatible ones are merged into it, and anything else is
+/// registered under a disambiguated name: first prefixed with `parent` (e.g. `User` inside
+/// `External` becomes `ExternalUser`), then with a numeric suffix.
+pub fn register_schema(
+    components: &mut oa::Components,
+    name: &str,
+    schema: oa::Schema,
+    parent: Option<&str>,
+) -> String {
+    for candidate in candidate_names(name, parent) {
+        match components.schemas.get_mut(&candidate) {
+            None => {
+                if candidate == name {
+                    info!(name=%candidate, "Added schema");
+                } else {
+                    warn!(name=%name, registered=%candidate, "Schema name conflict, registered under a disambiguated name");
+                }
+                components.schemas.insert(candidate.clone(), schema);
+                return candidate;
+            }
+            Some(RefOr::Item(existing)) if is_identical(existing, &schema) => {
+                debug!(name=%candidate, "Reusing identical schema");
+                return candidate;
+            }
+            Some(RefOr::Item(existing)) if is_compatible(existing, &schema) => {
+                merge_schema(existing, schema);
+                info!(name=%candidate, "Merged compatible schema into existing schema");
+                return candidate;
+            }
+            Some(existing) => {
+                debug!(name=%candidate, existing=?Lu(existing.clone()), "Schema name taken by an incompatible schema");
+            }
+        }
+    }
+    unreachable!("candidate_names is infinite")
+}
+
+/// Like [register_schema], but the name is fixed (e.g. because an operation already refers to
+/// it), so an incompatible schema replaces the existing one instead of being renamed.
+pub fn upsert_schema(components: &mut oa::Components, name: &str, schema: oa::Schema) {
+    match components.schemas.get_mut(name) {
+        Some(RefOr::Item(existing)) if is_identical(existing, &schema) => {
+            debug!(name=%name, "Reusing identical schema");
+        }
+        Some(RefOr::Item(existing)) if is_compatible(existing, &schema) => {
+            merge_schema(existing, schema);
+            info!(name=%name, "Merged compatible schema into existing schema");
+        }
+        Some(existing) => {
+            warn!(name=%name, existing=?Lu(existing.clone()), "Replacing incompatible schema");
+            components.schemas.insert(name, schema);
+        }
+        None => {
+            info!(name=%name, "Added schema");
+            components.schemas.insert(name, schema);
+        }
+    }
+}
+
+fn candidate_names<'a>(name: &'a str, parent: Option<&'a str>) -> impl Iterator<Item = String> + 'a {
+    let prefixed = parent
+        .filter(|p| !name.starts_with(p))
+        .map(|p| format!("{}{}", p, name));
+    std::iter::once(name.to_string())
+        .chain(prefixed)
+        .chain((2..).map(move |i| format!("{}{}", name, i)))
+}
+
+/// Two schemas are identical if they only differ in their examples, which are sampled values.
+fn is_identical(a: &oa::Schema, b: &oa::Schema) -> bool {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    strip_examples(&mut a);
+    strip_examples(&mut b);
+    a == b
+}
+
+/// Two schemas are compatible if they describe the same shape, allowing for fields that were
+/// missing from one of the samples. An empty object (what we produce for `null`) is compatible
+/// with anything.
+pub fn is_compatible(a: &oa::Schema, b: &oa::Schema) -> bool {
+    use oa::SchemaKind::Type as T;
+    if a.is_empty() || b.is_empty() {
+        return true;
+    }
+    match (&a.kind, &b.kind) {
+        (T(oa::Type::Object(a)), T(oa::Type::Object(b))) => {
+            let (small, large) = if a.properties.len() <= b.properties.len() {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            small.properties.iter().all(|(key, s)| {
+                large
+                    .properties
+                    .get(key)
+                    .is_some_and(|l| is_compatible_ref(s, l))
+            })
+        }
+        (T(oa::Type::Array(a)), T(oa::Type::Array(b))) => match (&a.items, &b.items) {
+            (Some(a), Some(b)) => is_compatible_ref(a, b),
+            _ => true,
+        },
+        (T(a), T(b)) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        _ => a == b,
+    }
+}
+
+fn is_compatible_ref(a: &RefOr<oa::Schema>, b: &RefOr<oa::Schema>) -> bool {
+    match (a, b) {
+        (RefOr::Item(a), RefOr::Item(b)) => is_compatible(a, b),
+        _ => a == b,
+    }
+}
+
+/// Merge `other` into `schema`. Properties are unioned, only properties required by both
+/// stay required, and the result is nullable if either side was.
+pub fn merge_schema(schema: &mut oa::Schema, other: oa::Schema) {
+    use oa::SchemaKind::Type as T;
+    let nullable = schema.data.nullable || other.data.nullable;
+    if schema.is_empty() {
+        *schema = other;
+        schema.data.nullable = nullable;
+        return;
+    }
+    schema.data.nullable = nullable;
+    match (&mut schema.kind, other.kind) {
+        (T(oa::Type::Object(a)), T(oa::Type::Object(b))) => {
+            a.required.retain(|r| b.required.contains(r));
+            for (key, s) in b.properties {
+                match (a.properties.get_mut(&key), s) {
+                    (Some(RefOr::Item(existing)), RefOr::Item(s)) => merge_schema(existing, s),
+                    (Some(_), _) => {}
+                    (None, s) => {
+                        a.properties.insert(key, s);
+                    }
+                }
+            }
+        }
+        (T(oa::Type::Array(a)), T(oa::Type::Array(b))) => match (&mut a.items, b.items) {
+            (Some(a), Some(b)) => {
+                if let (RefOr::Item(a), RefOr::Item(b)) = (a.as_mut(), *b) {
+                    merge_schema(a, b);
+                }
+            }
+            (items @ None, b) => *items = b,
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+/// Number of schemas that have to share a property group before it is extracted.
+const MIN_SCHEMAS_PER_BASE: usize = 3;
+
+/// Find groups of at least `min_group_size` identical properties (e.g. `id`, `created_at`,
+/// `updated_at`) shared by several object schemas, move each group into its own base component,
+/// and rewrite the schemas that share it as `allOf: [Base, {remaining properties}]`.
+///
+/// Returns the names of the base components that were created.
+pub fn extract_base_schemas(components: &mut oa::Components, min_group_size: usize) -> Vec<String> {
+    let mut created = Vec::new();
+    loop {
+        let objects = components
+            .schemas
+            .iter()
+            .filter_map(|(name, s)| Some((name.clone(), s.as_item()?)))
+            .filter_map(|(name, s)| match &s.kind {
+                oa::SchemaKind::Type(oa::Type::Object(o)) if o.additional_properties.is_none() => Some((name, o)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Count how many schemas have each (name, schema) property, most common first.
+        let mut counts: Vec<((&String, RefOr<oa::Schema>), usize)> = Vec::new();
+        for (_, o) in &objects {
+            for (key, prop) in o.properties.iter() {
+                let prop = without_example(prop);
+                match counts.iter_mut().find(|((k, p), _)| *k == key && *p == prop) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push(((key, prop), 1)),
+                }
+            }
+        }
+        counts.retain(|(_, n)| *n >= MIN_SCHEMAS_PER_BASE);
+        counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+        // Greedily grow the group while enough schemas still have every property in it.
+        let mut group = Vec::new();
+        let mut members = objects.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        for ((key, schema), _) in &counts {
+            let remaining = objects
+                .iter()
+                .filter(|(name, o)| {
+                    members.contains(name) && o.properties.get(*key).map(without_example).as_ref() == Some(schema)
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            if remaining.len() >= MIN_SCHEMAS_PER_BASE {
+                group.push(((*key).clone(), schema.clone()));
+                members = remaining;
+            }
+        }
+        if group.len() < min_group_size {
+            return created;
+        }
+
+        let mut base = oa::Schema::new_object();
+        for (key, schema) in &group {
+            base.properties_mut().insert(key, schema.clone());
+            let required_by_all = objects
+                .iter()
+                .filter(|(name, _)| members.contains(name))
+                .all(|(_, o)| o.required.contains(key));
+            if required_by_all {
+                base.add_required(key);
+            }
+        }
+        let base_name = candidate_names("Base", None)
+            .find(|n| !components.schemas.contains_key(n))
+            .unwrap();
+        info!(name=%base_name, properties=?group.iter().map(|(k, _)| k).collect::<Vec<_>>(), schemas=?members, "Extracted base schema");
+        components.schemas.insert(base_name.clone(), base.clone());
+
+        for name in &members {
+            let schema = components.schemas.get_mut2(name).unwrap();
+            let mut rest = std::mem::replace(schema, oa::Schema::new_all_of(vec![]));
+            for (key, _) in &group {
+                rest.properties_mut().shift_remove(key);
+                // Keys only some members require stay required in that member's own part.
+                if base.required().contains(key) {
+                    rest.remove_required(key);
+                }
+            }
+            schema.data = std::mem::take(&mut rest.data);
+            let mut all_of = vec![RefOr::schema_ref(&base_name)];
+            if !rest.properties().is_empty() || !rest.required().is_empty() {
+                all_of.push(RefOr::Item(rest));
+            }
+            schema.kind = oa::SchemaKind::AllOf { all_of };
+        }
+        created.push(base_name);
+    }
+}
+
+/// Like `RefOr::resolve`, but `None` instead of a panic when a reference, or a component it
+/// aliases, doesn't exist or is circular. Specs passed in by the user may contain such references.
+pub fn resolve_schema<'a>(spec: &'a oa::OpenAPI, schema: &'a RefOr<oa::Schema>) -> Option<&'a oa::Schema> {
+    let mut schema = schema;
+    let mut seen = Vec::new();
+    loop {
+        let reference = match schema {
+            RefOr::Item(schema) => return Some(schema),
+            RefOr::Reference { reference } => reference,
+        };
+        if seen.contains(&reference) {
+            return None;
+        }
+        seen.push(reference);
+        let path = reference.strip_prefix("#/components/schemas/")?;
+        schema = match path.split_once("/properties/") {
+            Some((name, property)) => spec.components.schemas.get(name)?.as_item()?.properties().get(property)?,
+            None => spec.components.schemas.get(path)?,
+        };
+    }
+}
+
+/// Sampled values differ between schemas, so they are ignored when comparing properties.
+fn without_example(schema: &RefOr<oa::Schema>) -> RefOr<oa::Schema> {
+    let mut schema = schema.clone();
+    if let RefOr::Item(s) = &mut schema {
+        strip_examples(s);
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(props: &[(&str, oa::Schema)]) -> oa::Schema {
+        let mut s = oa::Schema::new_object();
+        for (key, schema) in props {
+            s.properties_mut().insert(*key, schema.clone());
+            s.add_required(key);
+        }
+        s
+    }
+
+    #[test]
+    fn test_register_schema_conflicts() {
+        let mut components = oa::Components::default();
+        let user = object(&[("id", oa::Schema::new_integer()), ("email", oa::Schema::new_string())]);
+        let external_user = object(&[("uuid", oa::Schema::new_string()), ("role", oa::Schema::new_string())]);
+
+        assert_eq!(register_schema(&mut components, "User", user.clone(), None), "User");
+        assert_eq!(register_schema(&mut components, "User", user.clone(), Some("External")), "User");
+        assert_eq!(register_schema(&mut components, "User", external_user.clone(), Some("External")), "ExternalUser");
+        assert_eq!(register_schema(&mut components, "User", external_user.clone(), Some("External")), "ExternalUser");
+        assert_eq!(register_schema(&mut components, "User", oa::Schema::new_array_any(), Some("External")), "User2");
+        assert_eq!(components.schemas.len(), 3);
+
+        let mut other_user = user.clone();
+        other_user.properties_mut().get_mut2("email").unwrap().data.example = Some("bob@example.com".into());
+        assert_eq!(register_schema(&mut components, "User", other_user, None), "User");
+        assert_eq!(components.schemas.len(), 3);
+    }
+
+    #[test]
+    fn test_register_schema_merges_compatible() {
+        let mut components = oa::Components::default();
+        let full = object(&[("id", oa::Schema::new_integer()), ("deleted_at", oa::Schema::new_object())]);
+        let deleted = object(&[("id", oa::Schema::new_integer()), ("deleted_at", oa::Schema::new_string())]);
+        let partial = object(&[("id", oa::Schema::new_integer())]);
+        register_schema(&mut components, "User", full, None);
+        assert_eq!(register_schema(&mut components, "User", deleted, None), "User");
+        assert_eq!(register_schema(&mut components, "User", partial, None), "User");
+        let merged = components.schemas.get2("User").unwrap();
+        assert_eq!(merged.properties().len(), 2);
+        assert_eq!(merged.required(), &vec!["id".to_string()]);
+        let deleted_at = merged.properties().get2("deleted_at").unwrap();
+        assert!(matches!(deleted_at.kind, oa::SchemaKind::Type(oa::Type::String(_))));
+    }
+
+    #[test]
+    fn test_extract_base_schemas() {
+        let mut components = oa::Components::default();
+        let common = [
+            ("id", oa::Schema::new_integer()),
+            ("created_at", oa::Schema::new_string()),
+            ("updated_at", oa::Schema::new_string()),
+        ];
+        for (name, extra) in [("User", "email"), ("Order", "total"), ("Vendor", "website")] {
+            let mut props = common.to_vec();
+            props.push((extra, oa::Schema::new_string()));
+            components.schemas.insert(name, object(&props));
+        }
+        components.schemas.insert("Address", object(&[("id", oa::Schema::new_integer()), ("city", oa::Schema::new_string())]));
+
+        assert!(extract_base_schemas(&mut components, 4).is_empty());
+        assert_eq!(extract_base_schemas(&mut components, 3), vec!["Base".to_string()]);
+        let base = components.schemas.get2("Base").unwrap();
+        assert_eq!(base.properties().len(), 3);
+        assert_eq!(base.required().len(), 3);
+        let user = components.schemas.get2("User").unwrap();
+        let oa::SchemaKind::AllOf { all_of } = &user.kind else {
+            panic!("expected allOf");
+        };
+        assert_eq!(all_of[0].as_ref_str(), Some("#/components/schemas/Base"));
+        assert_eq!(all_of[1].as_item().unwrap().properties().keys().collect::<Vec<_>>(), vec!["email"]);
+        assert!(matches!(components.schemas.get2("Address").unwrap().kind, oa::SchemaKind::Type(oa::Type::Object(_))));
+    }
+
+    #[test]
+    fn test_extract_base_schemas_partly_required() {
+        let mut components = oa::Components::default();
+        for name in ["User", "Order", "Vendor"] {
+            let mut schema = object(&[("id", oa::Schema::new_integer()), ("name", oa::Schema::new_string())]);
+            if name == "Vendor" {
+                schema.remove_required("id");
+            }
+            components.schemas.insert(name, schema);
+        }
+        assert_eq!(extract_base_schemas(&mut components, 2), vec!["Base".to_string()]);
+        assert_eq!(components.schemas.get2("Base").unwrap().required(), &vec!["name".to_string()]);
+        let user = components.schemas.get2("User").unwrap();
+        let oa::SchemaKind::AllOf { all_of } = &user.kind else {
+            panic!("expected allOf");
+        };
+        assert_eq!(all_of[1].as_item().unwrap().required(), &vec!["id".to_string()]);
+        let vendor = components.schemas.get2("Vendor").unwrap();
+        let oa::SchemaKind::AllOf { all_of } = &vendor.kind else {
+            panic!("expected allOf");
+        };
+        assert_eq!(all_of.len(), 1);
+    }
+}
@@ -0,0 +1,306 @@
+use openapiv3 as oa;
+use openapiv3::RefOr;
+
+/// Merge a freshly `generated` spec into a hand-maintained `spec` without deleting or
+/// overwriting anything: paths, operations, parameters, request bodies, responses, headers,
+/// media types, schemas, properties and security schemes that `spec` lacks are added, and
+/// everything it already has (descriptions, fixed types, required lists) is kept as is.
+/// Returns a description of every addition.
+pub fn update_spec(spec: &mut oa::OpenAPI, generated: oa::OpenAPI) -> Vec<String> {
+    let mut added = Vec::new();
+    for (path, item) in generated.paths.paths {
+        let Some(item) = item.into_item() else {
+            continue;
+        };
+        let Some(existing) = spec.paths.paths.get_mut(&path) else {
+            added.push(format!("added path {}", path));
+            spec.paths.paths.insert(path, RefOr::Item(item));
+            continue;
+        };
+        let Some(existing) = existing.as_mut() else {
+            continue;
+        };
+        for (method, operation) in into_operations(item) {
+            let name = format!("{} {}", method.to_uppercase(), path);
+            let slot = operation_mut(existing, method);
+            match slot {
+                Some(existing) => update_operation(&name, existing, operation, &spec.components, &spec.security, &mut added),
+                None => {
+                    added.push(format!("added operation {}", name));
+                    *slot = Some(operation);
+                }
+            }
+        }
+    }
+    let generated_components = generated.components;
+    for (name, schema) in generated_components.schemas {
+        match (spec.components.schemas.get_mut(&name), schema) {
+            (None, schema) => {
+                added.push(format!("added schema {}", name));
+                spec.components.schemas.insert(name, schema);
+            }
+            (Some(RefOr::Item(existing)), RefOr::Item(schema)) => update_schema(&name, existing, schema, &mut added),
+            _ => {}
+        }
+    }
+    for (name, scheme) in generated_components.security_schemes {
+        if !spec.components.security_schemes.contains_key(&name) {
+            added.push(format!("added security scheme {}", name));
+            spec.components.security_schemes.insert(name, scheme);
+        }
+    }
+    added
+}
+
+pub(crate) fn into_operations(item: oa::PathItem) -> Vec<(&'static str, oa::Operation)> {
+    [
+        ("get", item.get),
+        ("put", item.put),
+        ("post", item.post),
+        ("delete", item.delete),
+        ("options", item.options),
+        ("head", item.head),
+        ("patch", item.patch),
+        ("trace", item.trace),
+    ]
+    .into_iter()
+    .filter_map(|(method, op)| Some((method, op?)))
+    .collect()
+}
+
+pub(crate) fn operation_mut<'a>(item: &'a mut oa::PathItem, method: &str) -> &'a mut Option<oa::Operation> {
+    match method {
+        "get" => &mut item.get,
+        "put" => &mut item.put,
+        "post" => &mut item.post,
+        "delete" => &mut item.delete,
+        "options" => &mut item.options,
+        "head" => &mut item.head,
+        "patch" => &mut item.patch,
+        "trace" => &mut item.trace,
+        _ => unreachable!("unknown method {}", method),
+    }
+}
+
+fn parameter_location(param: &oa::Parameter) -> &'static str {
+    match param.kind {
+        oa::ParameterKind::Query { .. } => "query",
+        oa::ParameterKind::Header { .. } => "header",
+        oa::ParameterKind::Path { .. } => "path",
+        oa::ParameterKind::Cookie { .. } => "cookie",
+    }
+}
+
+/// The (name, location) of a parameter, looking up references in `components`.
+fn parameter_key<'a>(param: &'a RefOr<oa::Parameter>, components: &'a oa::Components) -> Option<(&'a str, &'static str)> {
+    let param = match param {
+        RefOr::Item(param) => param,
+        RefOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/parameters/")?;
+            components.parameters.get2(name)?
+        }
+    };
+    Some((param.name.as_str(), parameter_location(param)))
+}
+
+/// Add the parameters, request body, responses, headers, media types and properties of
+/// `generated` that the operation `name` lacks. Observed security is only added to operations
+/// that have none of their own and don't inherit `global_security`.
+pub(crate) fn update_operation(
+    name: &str,
+    existing: &mut oa::Operation,
+    generated: oa::Operation,
+    components: &oa::Components,
+    global_security: &[oa::SecurityRequirement],
+    added: &mut Vec<String>,
+) {
+    for param in generated.parameters {
+        let Some((param_name, location)) = parameter_key(&param, components) else {
+            continue;
+        };
+        let exists = existing
+            .parameters
+            .iter()
+            .any(|p| parameter_key(p, components).is_some_and(|(n, l)| l == location && n.eq_ignore_ascii_case(param_name)));
+        if !exists {
+            added.push(format!("added {} parameter {} to {}", location, param_name, name));
+            existing.parameters.push(param);
+        }
+    }
+    match (&mut existing.request_body, generated.request_body) {
+        (None, Some(body)) => {
+            added.push(format!("added request body to {}", name));
+            existing.request_body = Some(body);
+        }
+        (Some(RefOr::Item(existing)), Some(RefOr::Item(body))) => {
+            update_content(&format!("request body of {}", name), &mut existing.content, body.content, added);
+        }
+        _ => {}
+    }
+    for (status, response) in generated.responses.responses {
+        let Some(current) = existing.responses.responses.get_mut(&status) else {
+            added.push(format!("added response {} to {}", status, name));
+            existing.responses.responses.insert(status, response);
+            continue;
+        };
+        let (Some(current), Some(response)) = (current.as_mut(), response.into_item()) else {
+            continue;
+        };
+        let location = format!("response {} of {}", status, name);
+        for (header, value) in response.headers {
+            if !current.headers.keys().any(|h| h.eq_ignore_ascii_case(&header)) {
+                added.push(format!("added header {} to {}", header, location));
+                current.headers.insert(header, value);
+            }
+        }
+        update_content(&location, &mut current.content, response.content, added);
+    }
+    // An empty list would make an operation that relies on the global security public.
+    let observed = generated.security.filter(|s| !s.is_empty());
+    if existing.security.is_none() && global_security.is_empty() && observed.is_some() {
+        added.push(format!("added security to {}", name));
+        existing.security = observed;
+    }
+}
+
+fn update_content(
+    location: &str,
+    existing: &mut indexmap::IndexMap<String, oa::MediaType>,
+    generated: indexmap::IndexMap<String, oa::MediaType>,
+    added: &mut Vec<String>,
+) {
+    for (mime, media) in generated {
+        let Some(current) = existing.get_mut(&mime) else {
+            added.push(format!("added media type {} to {}", mime, location));
+            existing.insert(mime, media);
+            continue;
+        };
+        match (&mut current.schema, media.schema) {
+            (None, Some(schema)) => {
+                added.push(format!("added schema to {} of {}", mime, location));
+                current.schema = Some(schema);
+            }
+            (Some(RefOr::Item(existing)), Some(RefOr::Item(schema))) => {
+                update_schema(&format!("{} of {}", mime, location), existing, schema, added);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Add the properties of `generated` that `existing` lacks, recursing into inline properties
+/// and array items. Nothing else about `existing` changes.
+fn update_schema(location: &str, existing: &mut oa::Schema, generated: oa::Schema, added: &mut Vec<String>) {
+    use oa::SchemaKind::Type as T;
+    match (&mut existing.kind, generated.kind) {
+        (T(oa::Type::Object(existing)), T(oa::Type::Object(generated))) => {
+            for (name, property) in generated.properties {
+                match (existing.properties.get_mut(&name), property) {
+                    (None, property) => {
+                        added.push(format!("added property {} to {}", name, location));
+                        existing.properties.insert(name, property);
+                    }
+                    (Some(RefOr::Item(existing)), RefOr::Item(property)) => {
+                        update_schema(&format!("{}.{}", location, name), existing, property, added);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        (T(oa::Type::Array(existing)), T(oa::Type::Array(generated))) => {
+            if let (Some(RefOr::Item(existing)), Some(RefOr::Item(items))) = (existing.items.as_deref_mut(), generated.items.map(|i| *i)) {
+                update_schema(&format!("{}[]", location), existing, items, added);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_update_spec_keeps_inherited_security() -> anyhow::Result<()> {
+        let spec = |security: serde_json::Value| -> serde_json::Result<oa::OpenAPI> {
+            serde_json::from_value(json!({
+                "openapi": "3.0.3",
+                "info": {"title": "", "version": ""},
+                "security": security,
+                "paths": {"/users": {"get": {"responses": {}}}},
+            }))
+        };
+        let generated = |security: serde_json::Value| -> serde_json::Result<oa::OpenAPI> {
+            serde_json::from_value(json!({
+                "openapi": "3.0.3",
+                "info": {"title": "", "version": ""},
+                "paths": {"/users": {"get": {"responses": {}, "security": security}}},
+            }))
+        };
+        let security = |spec: &oa::OpenAPI| spec.paths.paths["/users"].as_item().unwrap().get.as_ref().unwrap().security.clone();
+
+        let mut global = spec(json!([{"BearerAuth": []}]))?;
+        assert!(update_spec(&mut global, generated(json!([{"SessionidCookie": []}]))?).is_empty());
+        assert_eq!(security(&global), None);
+
+        let mut public = spec(json!([]))?;
+        assert!(update_spec(&mut public, generated(json!([]))?).is_empty());
+        assert_eq!(security(&public), None);
+        assert_eq!(update_spec(&mut public, generated(json!([{"BearerAuth": []}]))?), vec!["added security to GET /users"]);
+        assert_eq!(security(&public).unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_spec_keeps_existing_fields() -> anyhow::Result<()> {
+        let mut spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Hand written", "version": "1"},
+            "paths": {
+                "/users": {"get": {
+                    "description": "List users.",
+                    "parameters": [{"name": "page", "in": "query", "description": "Page number.", "schema": {"type": "integer"}}],
+                    "responses": {"200": {"description": "The users."}},
+                }},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "description": "A user.", "properties": {"id": {"type": "string", "format": "uuid"}}},
+            }},
+        }))?;
+        let generated: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "", "version": ""},
+            "paths": {
+                "/users": {"get": {
+                    "parameters": [
+                        {"name": "page", "in": "query", "schema": {"type": "string"}},
+                        {"name": "sort", "in": "query", "schema": {"type": "string"}},
+                    ],
+                    "responses": {"200": {"description": ""}, "404": {"description": ""}},
+                }},
+                "/teams": {"get": {"responses": {}}},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "properties": {"id": {"type": "integer"}, "email": {"type": "string"}}},
+            }},
+        }))?;
+        let added = update_spec(&mut spec, generated);
+        assert_eq!(added, vec![
+            "added query parameter sort to GET /users",
+            "added response 404 to GET /users",
+            "added path /teams",
+            "added property email to User",
+        ]);
+        assert_eq!(spec.info.title, "Hand written");
+        let op = spec.paths.paths["/users"].as_item().unwrap().get.as_ref().unwrap();
+        assert_eq!(op.description.as_deref(), Some("List users."));
+        assert_eq!(op.parameters[0].as_item().unwrap().description.as_deref(), Some("Page number."));
+        assert_eq!(op.responses.responses[&oa::StatusCode::Code(200)].as_item().unwrap().description, "The users.");
+        let user = spec.components.schemas.get2("User").unwrap();
+        assert_eq!(user.data.description.as_deref(), Some("A user."));
+        let id = user.properties().get2("id").unwrap();
+        assert!(matches!(id.kind, oa::SchemaKind::Type(oa::Type::String(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,668 @@
+use anyhow::Result;
+use indexmap::IndexSet;
+use openapiv3 as oa;
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+use url::Url;
+
+pub const VERSION: &str = "2.0";
+
+/// Keys of a parameter or header schema that Swagger 2.0 allows on the parameter itself.
+const INLINE_SCHEMA_KEYS: [&str; 16] = [
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "maximum",
+    "minimum",
+    "exclusiveMaximum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+const FORM_MIME_TYPES: [&str; 2] = ["application/x-www-form-urlencoded", "multipart/form-data"];
+
+/// Convert a spec to Swagger 2.0. Constructs that Swagger 2.0 can't represent (e.g. `oneOf`,
+/// cookie parameters, OpenID Connect) are dropped or approximated, and each is reported as
+/// `{location}: {message}` in the returned warnings.
+pub fn to_swagger2(spec: &oa::OpenAPI) -> Result<(Value, Vec<String>)> {
+    let mut v3 = serde_json::to_value(spec)?;
+    let mut converter = Converter::default();
+    converter.rewrite_refs("#", &mut v3);
+    let doc = converter.document(&v3);
+    Ok((Value::Object(doc), converter.warnings))
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    warnings: Vec<String>,
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn copy(from: &Value, to: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        if let Some(v) = from.get(*key) {
+            to.insert(key.to_string(), v.clone());
+        }
+    }
+}
+
+fn copy_extensions(from: &Value, to: &mut Map<String, Value>) {
+    if let Some(map) = from.as_object() {
+        for (k, v) in map.iter().filter(|(k, _)| k.starts_with("x-")) {
+            to.insert(k.clone(), v.clone());
+        }
+    }
+}
+
+impl Converter {
+    fn warn(&mut self, location: &str, message: impl Display) {
+        self.warnings.push(format!("{}: {}", location, message));
+    }
+
+    /// Point schema references at `definitions`. Other component references have no
+    /// counterpart, since only schemas are generated.
+    fn rewrite_refs(&mut self, location: &str, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    let location = format!("{}/{}", location, escape_pointer(key));
+                    if let (true, Value::String(r)) = (key == "$ref", &mut *v) {
+                        if let Some(name) = r.strip_prefix("#/components/schemas/") {
+                            *r = format!("#/definitions/{}", name);
+                        } else if r.starts_with("#/components/") {
+                            let message = format!("reference {} is not supported", r);
+                            self.warn(&location, message);
+                        }
+                    } else if key != "example" && key != "examples" {
+                        self.rewrite_refs(&location, v);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, v) in items.iter_mut().enumerate() {
+                    self.rewrite_refs(&format!("{}/{}", location, i), v);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn document(&mut self, v3: &Value) -> Map<String, Value> {
+        let mut doc = Map::new();
+        doc.insert("swagger".to_string(), Value::from(VERSION));
+        copy(v3, &mut doc, &["info"]);
+        self.servers(v3, &mut doc);
+        let mut paths = Map::new();
+        if let Some(v3_paths) = v3.get("paths").and_then(Value::as_object) {
+            for (path, item) in v3_paths {
+                let location = format!("#/paths/{}", escape_pointer(path));
+                paths.insert(path.clone(), Value::Object(self.path_item(&location, item)));
+            }
+        }
+        doc.insert("paths".to_string(), Value::Object(paths));
+        if let Some(schemas) = v3.pointer("/components/schemas").and_then(Value::as_object) {
+            let definitions = schemas
+                .iter()
+                .map(|(name, s)| {
+                    let location = format!("#/components/schemas/{}", escape_pointer(name));
+                    (name.clone(), self.schema(&location, s))
+                })
+                .collect::<Map<_, _>>();
+            if !definitions.is_empty() {
+                doc.insert("definitions".to_string(), Value::Object(definitions));
+            }
+        }
+        let dropped = self.security_definitions(v3, &mut doc);
+        if let Some(security) = v3.get("security") {
+            match without_schemes(security, &dropped) {
+                Some(security) => {
+                    doc.insert("security".to_string(), security);
+                }
+                None => self.warn("#/security", "every requirement uses an unsupported security scheme, so none is kept"),
+            }
+        }
+        copy(v3, &mut doc, &["tags", "externalDocs"]);
+        copy_extensions(v3, &mut doc);
+        doc
+    }
+
+    /// Swagger 2.0 describes a single server as `schemes`, `host` and `basePath`.
+    fn servers(&mut self, v3: &Value, doc: &mut Map<String, Value>) {
+        let Some(servers) = v3.get("servers").and_then(Value::as_array) else {
+            return;
+        };
+        let Some(server) = servers.first() else {
+            return;
+        };
+        if servers.len() > 1 {
+            self.warn("#/servers", "only the first server is kept");
+        }
+        if server.get("variables").is_some_and(|v| v.as_object().is_some_and(|v| !v.is_empty())) {
+            self.warn("#/servers/0/variables", "server variables are not supported");
+        }
+        let url = server.get("url").and_then(Value::as_str).unwrap_or_default();
+        match Url::parse(url) {
+            Ok(url) => {
+                if let Some(host) = url.host_str() {
+                    let host = match url.port() {
+                        Some(port) => format!("{}:{}", host, port),
+                        None => host.to_string(),
+                    };
+                    doc.insert("host".to_string(), Value::from(host));
+                }
+                doc.insert("basePath".to_string(), Value::from(url.path()));
+                doc.insert("schemes".to_string(), json!([url.scheme()]));
+            }
+            // A relative server URL is just the base path.
+            Err(_) if url.starts_with('/') => {
+                doc.insert("basePath".to_string(), Value::from(url));
+            }
+            Err(_) => self.warn("#/servers/0/url", format!("cannot parse server url {:?}", url)),
+        }
+    }
+
+    fn path_item(&mut self, location: &str, item: &Value) -> Map<String, Value> {
+        let mut out = Map::new();
+        let Some(map) = item.as_object() else {
+            return out;
+        };
+        for (key, value) in map {
+            let location = format!("{}/{}", location, key);
+            match key.as_str() {
+                "get" | "put" | "post" | "delete" | "options" | "head" | "patch" => {
+                    out.insert(key.clone(), Value::Object(self.operation(&location, value)));
+                }
+                "parameters" => {
+                    let params = self.parameters(&location, value);
+                    out.insert(key.clone(), Value::Array(params));
+                }
+                "$ref" => {
+                    out.insert(key.clone(), value.clone());
+                }
+                k if k.starts_with("x-") => {
+                    out.insert(key.clone(), value.clone());
+                }
+                "summary" | "description" => {}
+                _ => self.warn(&location, format!("{} is not supported", key)),
+            }
+        }
+        out
+    }
+
+    fn operation(&mut self, location: &str, op: &Value) -> Map<String, Value> {
+        let mut out = Map::new();
+        copy(op, &mut out, &["tags", "summary", "description", "externalDocs", "operationId"]);
+        let mut parameters = match op.get("parameters") {
+            Some(params) => self.parameters(&format!("{}/parameters", location), params),
+            None => Vec::new(),
+        };
+        if let Some(body) = op.get("requestBody") {
+            let (params, consumes) = self.request_body(&format!("{}/requestBody", location), body);
+            parameters.extend(params);
+            if !consumes.is_empty() {
+                out.insert("consumes".to_string(), json!(consumes));
+            }
+        }
+        if !parameters.is_empty() {
+            out.insert("parameters".to_string(), Value::Array(parameters));
+        }
+        let mut produces = IndexSet::new();
+        let mut responses = Map::new();
+        if let Some(v3_responses) = op.get("responses").and_then(Value::as_object) {
+            for (status, response) in v3_responses {
+                let location = format!("{}/responses/{}", location, status);
+                let response = self.response(&location, response, &mut produces);
+                responses.insert(status.clone(), Value::Object(response));
+            }
+        }
+        if !produces.is_empty() {
+            out.insert("produces".to_string(), json!(produces));
+        }
+        out.insert("responses".to_string(), Value::Object(responses));
+        copy(op, &mut out, &["deprecated", "security"]);
+        for key in ["callbacks", "servers"] {
+            if op.get(key).is_some_and(|v| v.as_object().is_none_or(|m| !m.is_empty())) {
+                self.warn(&format!("{}/{}", location, key), format!("{} are not supported", key));
+            }
+        }
+        copy_extensions(op, &mut out);
+        out
+    }
+
+    fn parameters(&mut self, location: &str, params: &Value) -> Vec<Value> {
+        let Some(params) = params.as_array() else {
+            return Vec::new();
+        };
+        params
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| self.parameter(&format!("{}/{}", location, i), p))
+            .collect()
+    }
+
+    fn parameter(&mut self, location: &str, param: &Value) -> Option<Value> {
+        if param.get("$ref").is_some() {
+            self.warn(location, "parameter references are not supported");
+            return None;
+        }
+        let name = param.get("name").and_then(Value::as_str).unwrap_or_default();
+        let location_in = param.get("in").and_then(Value::as_str).unwrap_or_default();
+        if location_in == "cookie" {
+            self.warn(location, format!("cookie parameter {} is not supported", name));
+            return None;
+        }
+        let Some(schema) = param.get("schema") else {
+            self.warn(location, format!("parameter {} without a schema is not supported", name));
+            return None;
+        };
+        let mut out = Map::new();
+        copy(param, &mut out, &["name", "in", "description", "required"]);
+        if !self.inline_schema(location, name, schema, &mut out) {
+            return None;
+        }
+        if out.get("type") == Some(&Value::from("array")) {
+            let style = param.get("style").and_then(Value::as_str).unwrap_or(match location_in {
+                "query" => "form",
+                _ => "simple",
+            });
+            let explode = param.get("explode").and_then(Value::as_bool).unwrap_or(style == "form");
+            let format = match (style, explode) {
+                ("form", true) => "multi",
+                ("pipeDelimited", _) => "pipes",
+                ("spaceDelimited", _) => "ssv",
+                _ => "csv",
+            };
+            out.insert("collectionFormat".to_string(), Value::from(format));
+        }
+        let example = param.get("example").cloned().or_else(|| {
+            let examples = param.get("examples")?.as_object()?;
+            examples.values().find_map(|e| e.get("value").cloned())
+        });
+        if let Some(example) = example {
+            out.insert("x-example".to_string(), example);
+        }
+        copy_extensions(param, &mut out);
+        Some(Value::Object(out))
+    }
+
+    /// Move the keys of a non-body parameter or header schema onto the parameter itself.
+    /// Returns false if the schema has no inline form (objects and references).
+    fn inline_schema(&mut self, location: &str, name: &str, schema: &Value, out: &mut Map<String, Value>) -> bool {
+        let schema = self.schema(&format!("{}/schema", location), schema);
+        let is_object = schema.get("$ref").is_some()
+            || schema.get("type").is_some_and(|t| t == "object")
+            || schema.get("items").is_some_and(|i| i.get("$ref").is_some() || i.get("type").is_some_and(|t| t == "object"));
+        if is_object {
+            self.warn(location, format!("object parameter {} is not supported", name));
+            return false;
+        }
+        copy(&schema, out, &INLINE_SCHEMA_KEYS);
+        if !out.contains_key("type") {
+            out.insert("type".to_string(), Value::from("string"));
+        }
+        true
+    }
+
+    /// A request body becomes a `body` parameter, or `formData` parameters for form encodings.
+    fn request_body(&mut self, location: &str, body: &Value) -> (Vec<Value>, Vec<String>) {
+        let Some(content) = body.get("content").and_then(Value::as_object) else {
+            return (Vec::new(), Vec::new());
+        };
+        let consumes = content.keys().cloned().collect::<Vec<_>>();
+        let Some((mime, media)) = content.iter().next() else {
+            return (Vec::new(), consumes);
+        };
+        if content.values().any(|m| m.get("schema") != media.get("schema")) {
+            self.warn(location, "only the schema of the first media type is kept");
+        }
+        let location = format!("{}/content/{}", location, escape_pointer(mime));
+        let schema = media.get("schema").cloned().unwrap_or(json!({}));
+        if FORM_MIME_TYPES.contains(&mime.as_str()) {
+            let required = schema.get("required").cloned().unwrap_or(json!([]));
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                self.warn(&location, "form bodies without properties are not supported");
+                return (Vec::new(), consumes);
+            };
+            let params = properties
+                .iter()
+                .filter_map(|(name, prop)| {
+                    let location = format!("{}/schema/properties/{}", location, escape_pointer(name));
+                    let mut out = Map::new();
+                    out.insert("name".to_string(), Value::from(name.clone()));
+                    out.insert("in".to_string(), Value::from("formData"));
+                    let is_required = required.as_array().is_some_and(|r| r.contains(&Value::from(name.clone())));
+                    out.insert("required".to_string(), Value::from(is_required));
+                    if prop.get("format").is_some_and(|f| f == "binary") {
+                        out.insert("type".to_string(), Value::from("file"));
+                    } else if !self.inline_schema(&location, name, prop, &mut out) {
+                        return None;
+                    }
+                    Some(Value::Object(out))
+                })
+                .collect();
+            return (params, consumes);
+        }
+        let mut out = Map::new();
+        out.insert("name".to_string(), Value::from("body"));
+        out.insert("in".to_string(), Value::from("body"));
+        copy(body, &mut out, &["description", "required"]);
+        out.insert("schema".to_string(), self.schema(&format!("{}/schema", location), &schema));
+        (vec![Value::Object(out)], consumes)
+    }
+
+    fn response(&mut self, location: &str, response: &Value, produces: &mut IndexSet<String>) -> Map<String, Value> {
+        let mut out = Map::new();
+        let description = response.get("description").cloned().unwrap_or(Value::from(""));
+        out.insert("description".to_string(), description);
+        if let Some(content) = response.get("content").and_then(Value::as_object) {
+            produces.extend(content.keys().cloned());
+            if let Some((mime, media)) = content.iter().next() {
+                let location = format!("{}/content/{}", location, escape_pointer(mime));
+                if let Some(schema) = media.get("schema") {
+                    out.insert("schema".to_string(), self.schema(&format!("{}/schema", location), schema));
+                }
+                let examples = content
+                    .iter()
+                    .filter_map(|(mime, media)| {
+                        let example = media.get("example").cloned().or_else(|| {
+                            let examples = media.get("examples")?.as_object()?;
+                            examples.values().find_map(|e| e.get("value").cloned())
+                        })?;
+                        Some((mime.clone(), example))
+                    })
+                    .collect::<Map<_, _>>();
+                if !examples.is_empty() {
+                    out.insert("examples".to_string(), Value::Object(examples));
+                }
+            }
+            let first = content.values().next().and_then(|m| m.get("schema"));
+            if content.values().any(|m| m.get("schema") != first) {
+                self.warn(location, "only the schema of the first media type is kept");
+            }
+        }
+        if let Some(headers) = response.get("headers").and_then(Value::as_object) {
+            let mut converted = Map::new();
+            for (name, header) in headers {
+                let location = format!("{}/headers/{}", location, escape_pointer(name));
+                let Some(schema) = header.get("schema") else {
+                    self.warn(&location, format!("header {} without a schema is not supported", name));
+                    continue;
+                };
+                let mut out = Map::new();
+                copy(header, &mut out, &["description"]);
+                if self.inline_schema(&location, name, schema, &mut out) {
+                    converted.insert(name.clone(), Value::Object(out));
+                }
+            }
+            if !converted.is_empty() {
+                out.insert("headers".to_string(), Value::Object(converted));
+            }
+        }
+        if response.get("links").is_some_and(|l| l.as_object().is_some_and(|l| !l.is_empty())) {
+            self.warn(&format!("{}/links", location), "links are not supported");
+        }
+        out
+    }
+
+    fn schema(&mut self, location: &str, schema: &Value) -> Value {
+        let Value::Object(map) = schema else {
+            return schema.clone();
+        };
+        let mut out = Map::new();
+        for (key, value) in map {
+            let location = format!("{}/{}", location, escape_pointer(key));
+            match key.as_str() {
+                "nullable" => {
+                    out.insert("x-nullable".to_string(), value.clone());
+                }
+                "deprecated" => {
+                    out.insert("x-deprecated".to_string(), value.clone());
+                }
+                "properties" => {
+                    let properties = value
+                        .as_object()
+                        .into_iter()
+                        .flatten()
+                        .map(|(name, s)| {
+                            let location = format!("{}/{}", location, escape_pointer(name));
+                            (name.clone(), self.schema(&location, s))
+                        })
+                        .collect();
+                    out.insert(key.clone(), Value::Object(properties));
+                }
+                "items" | "additionalProperties" => {
+                    out.insert(key.clone(), self.schema(&location, value));
+                }
+                "allOf" => {
+                    let schemas = value
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(i, s)| self.schema(&format!("{}/{}", location, i), s))
+                        .collect();
+                    out.insert(key.clone(), Value::Array(schemas));
+                }
+                "oneOf" | "anyOf" => {
+                    self.warn(&location, format!("{} is not supported, kept as x-{}", key, key));
+                    out.insert(format!("x-{}", key), value.clone());
+                }
+                "not" | "writeOnly" => {
+                    self.warn(&location, format!("{} is not supported", key));
+                }
+                "discriminator" => {
+                    if let Some(property) = value.get("propertyName") {
+                        out.insert(key.clone(), property.clone());
+                    }
+                    if value.get("mapping").is_some_and(|m| m.as_object().is_some_and(|m| !m.is_empty())) {
+                        self.warn(&location, "discriminator mapping is not supported");
+                    }
+                }
+                _ => {
+                    out.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Value::Object(out)
+    }
+
+    /// Convert `securitySchemes` to `securityDefinitions`, returning the names of the schemes
+    /// that were dropped.
+    fn security_definitions(&mut self, v3: &Value, doc: &mut Map<String, Value>) -> Vec<String> {
+        let mut dropped = Vec::new();
+        let Some(schemes) = v3.pointer("/components/securitySchemes").and_then(Value::as_object) else {
+            return dropped;
+        };
+        let mut definitions = Map::new();
+        for (name, scheme) in schemes {
+            let location = format!("#/components/securitySchemes/{}", escape_pointer(name));
+            let typ = scheme.get("type").and_then(Value::as_str).unwrap_or_default();
+            let mut out = Map::new();
+            copy(scheme, &mut out, &["description"]);
+            match typ {
+                "apiKey" if scheme.get("in").is_some_and(|l| l != "cookie") => {
+                    out.insert("type".to_string(), Value::from("apiKey"));
+                    copy(scheme, &mut out, &["name", "in"]);
+                }
+                "http" if scheme.get("scheme").is_some_and(|s| s == "basic") => {
+                    out.insert("type".to_string(), Value::from("basic"));
+                }
+                "http" if scheme.get("scheme").is_some_and(|s| s == "bearer") => {
+                    self.warn(&location, "bearer auth is exported as an API key in the Authorization header");
+                    out.insert("type".to_string(), Value::from("apiKey"));
+                    out.insert("name".to_string(), Value::from("Authorization"));
+                    out.insert("in".to_string(), Value::from("header"));
+                }
+                "oauth2" => {
+                    let Some(flows) = scheme.get("flows").and_then(Value::as_object) else {
+                        dropped.push(name.clone());
+                        continue;
+                    };
+                    if flows.len() > 1 {
+                        self.warn(&location, "only the first OAuth2 flow is kept");
+                    }
+                    let Some((flow_name, flow)) = flows.iter().next() else {
+                        dropped.push(name.clone());
+                        continue;
+                    };
+                    let flow_name = match flow_name.as_str() {
+                        "authorizationCode" => "accessCode",
+                        "clientCredentials" => "application",
+                        other => other,
+                    };
+                    out.insert("type".to_string(), Value::from("oauth2"));
+                    out.insert("flow".to_string(), Value::from(flow_name));
+                    copy(flow, &mut out, &["authorizationUrl", "tokenUrl", "scopes"]);
+                }
+                _ => {
+                    self.warn(&location, format!("security scheme {} is not supported", name));
+                    dropped.push(name.clone());
+                    continue;
+                }
+            }
+            definitions.insert(name.clone(), Value::Object(out));
+        }
+        if !definitions.is_empty() {
+            doc.insert("securityDefinitions".to_string(), Value::Object(definitions));
+        }
+        if !dropped.is_empty() {
+            self.remove_security_requirements(doc, &dropped);
+        }
+        dropped
+    }
+
+    fn remove_security_requirements(&mut self, doc: &mut Map<String, Value>, dropped: &[String]) {
+        let Some(paths) = doc.get_mut("paths").and_then(Value::as_object_mut) else {
+            return;
+        };
+        for (path, item) in paths.iter_mut().filter_map(|(path, item)| Some((path, item.as_object_mut()?))) {
+            for (method, op) in item.iter_mut().filter_map(|(method, op)| Some((method, op.as_object_mut()?))) {
+                let Some(security) = op.get("security") else {
+                    continue;
+                };
+                match without_schemes(security, dropped) {
+                    Some(security) => {
+                        op.insert("security".to_string(), security);
+                    }
+                    None => {
+                        op.remove("security");
+                        let location = format!("#/paths/{}/{}/security", escape_pointer(path), method);
+                        self.warn(&location, "every requirement uses an unsupported security scheme, so the global security applies instead");
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Remove requirements on dropped schemes from a list of security requirements. A requirement
+/// that only used dropped schemes is removed too. Returns `None` if that leaves no requirements,
+/// since an empty list (or `[{}]`) would claim that no credentials are needed.
+fn without_schemes(security: &Value, dropped: &[String]) -> Option<Value> {
+    let Some(requirements) = security.as_array() else {
+        return Some(security.clone());
+    };
+    let kept = requirements
+        .iter()
+        .filter_map(|r| {
+            let r = r.as_object()?;
+            if r.is_empty() {
+                return Some(Value::Object(r.clone()));
+            }
+            let r = r
+                .iter()
+                .filter(|(name, _)| !dropped.contains(name))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Map<_, _>>();
+            (!r.is_empty()).then_some(Value::Object(r))
+        })
+        .collect::<Vec<_>>();
+    if kept.is_empty() && !requirements.is_empty() {
+        return None;
+    }
+    Some(Value::Array(kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_swagger2() -> Result<()> {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "servers": [{"url": "https://api.example.com:8443/v1"}],
+            "paths": {
+                "/users": {
+                    "post": {
+                        "operationId": "postUsers",
+                        "parameters": [
+                            {"name": "tags", "in": "query", "style": "form", "explode": false, "schema": {"type": "array", "items": {"type": "string"}}},
+                            {"name": "theme", "in": "cookie", "schema": {"type": "string"}},
+                        ],
+                        "requestBody": {
+                            "required": true,
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}},
+                        },
+                        "responses": {
+                            "201": {
+                                "description": "",
+                                "headers": {"Location": {"schema": {"type": "string"}}},
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}},
+                            },
+                        },
+                        "security": [{"Session": []}, {"BearerAuth": []}],
+                    },
+                    "get": {
+                        "responses": {"200": {"description": ""}},
+                        "security": [{"Session": []}],
+                    },
+                },
+            },
+            "components": {
+                "schemas": {
+                    "User": {"type": "object", "properties": {"name": {"type": "string", "nullable": true}}},
+                    "Feed": {"oneOf": [{"$ref": "#/components/schemas/User"}]},
+                },
+                "securitySchemes": {
+                    "Session": {"type": "apiKey", "in": "cookie", "name": "sessionid"},
+                    "BearerAuth": {"type": "http", "scheme": "bearer"},
+                },
+            },
+        }))?;
+        let (doc, warnings) = to_swagger2(&spec)?;
+        assert_eq!(doc["host"], "api.example.com:8443");
+        assert_eq!(doc["basePath"], "/v1");
+        let op = &doc["paths"]["/users"]["post"];
+        assert_eq!(op["consumes"], json!(["application/json"]));
+        assert_eq!(op["produces"], json!(["application/json"]));
+        assert_eq!(
+            op["parameters"],
+            json!([
+                {"name": "tags", "in": "query", "type": "array", "items": {"type": "string"}, "collectionFormat": "csv"},
+                {"name": "body", "in": "body", "required": true, "schema": {"$ref": "#/definitions/User"}},
+            ])
+        );
+        assert_eq!(op["responses"]["201"]["headers"]["Location"], json!({"type": "string"}));
+        assert_eq!(op["security"], json!([{"BearerAuth": []}]));
+        assert_eq!(doc["definitions"]["User"]["properties"]["name"]["x-nullable"], true);
+        assert_eq!(doc["securityDefinitions"]["BearerAuth"]["in"], "header");
+        assert!(doc["paths"]["/users"]["get"].get("security").is_none());
+        assert!(warnings.iter().any(|w| w.starts_with("#/paths/~1users/get/security:")), "{:?}", warnings);
+        assert_eq!(warnings.len(), 5, "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.starts_with("#/components/schemas/Feed/oneOf:")));
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use openapiv3 as oa;
+use serde_json::{Map, Value};
+
+pub const VERSION: &str = "3.1.0";
+
+/// Convert a 3.0 spec to OpenAPI 3.1, whose schemas are JSON Schema 2020-12: `nullable` becomes a
+/// `null` entry in a type array, `example` becomes `examples`, single-value enums become `const`,
+/// and boolean `exclusiveMinimum`/`exclusiveMaximum` become numbers.
+pub fn to_v3_1(spec: &oa::OpenAPI) -> Result<Value> {
+    let mut value = serde_json::to_value(spec)?;
+    value["openapi"] = Value::from(VERSION);
+    if let Some(schemas) = value.pointer_mut("/components/schemas").and_then(Value::as_object_mut) {
+        schemas.values_mut().for_each(convert_schema);
+    }
+    for key in ["paths", "components"] {
+        if let Some(v) = value.get_mut(key) {
+            convert_nested_schemas(v);
+        }
+    }
+    Ok(value)
+}
+
+/// Convert the schemas under `schema` keys (of parameters, headers and media types).
+fn convert_nested_schemas(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if key == "schema" {
+                    convert_schema(v);
+                } else if key != "schemas" && key != "examples" && key != "example" {
+                    convert_nested_schemas(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(convert_nested_schemas),
+        _ => {}
+    }
+}
+
+fn convert_schema(schema: &mut Value) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    if let Some(example) = map.remove("example") {
+        map.insert("examples".to_string(), Value::Array(vec![example]));
+    }
+    if let Some(Value::Array(values)) = map.get("enum") {
+        if values.len() == 1 {
+            let value = values[0].clone();
+            map.remove("enum");
+            map.insert("const".to_string(), value);
+        }
+    }
+    for (exclusive, bound) in [("exclusiveMinimum", "minimum"), ("exclusiveMaximum", "maximum")] {
+        match map.remove(exclusive) {
+            Some(Value::Bool(true)) => {
+                if let Some(bound) = map.remove(bound) {
+                    map.insert(exclusive.to_string(), bound);
+                }
+            }
+            Some(Value::Bool(false)) | None => {}
+            Some(v) => {
+                map.insert(exclusive.to_string(), v);
+            }
+        }
+    }
+    if map.remove("nullable") == Some(Value::Bool(true)) {
+        add_null(map);
+    }
+    for key in ["properties", "patternProperties"] {
+        if let Some(Value::Object(properties)) = map.get_mut(key) {
+            properties.values_mut().for_each(convert_schema);
+        }
+    }
+    for key in ["items", "additionalProperties", "not"] {
+        if let Some(v) = map.get_mut(key) {
+            convert_schema(v);
+        }
+    }
+    for key in ["allOf", "oneOf", "anyOf"] {
+        if let Some(Value::Array(schemas)) = map.get_mut(key) {
+            schemas.iter_mut().for_each(convert_schema);
+        }
+    }
+}
+
+/// Allow `null` in a schema that had `nullable: true`.
+fn add_null(map: &mut Map<String, Value>) {
+    // Our placeholder for properties that were only ever seen as `null`.
+    let only_null = map.get("type") == Some(&Value::from("object"))
+        && !map.contains_key("properties")
+        && !map.contains_key("additionalProperties");
+    if only_null {
+        map.insert("type".to_string(), Value::from("null"));
+        return;
+    }
+    match map.get_mut("type") {
+        Some(Value::String(t)) => {
+            let t = Value::from(t.clone());
+            map.insert("type".to_string(), Value::Array(vec![t, Value::from("null")]));
+        }
+        Some(_) => {}
+        None => {
+            let inner = Value::Object(std::mem::take(map));
+            map.insert(
+                "anyOf".to_string(),
+                Value::Array(vec![inner, serde_json::json!({"type": "null"})]),
+            );
+            return;
+        }
+    }
+    if let Some(Value::Array(values)) = map.get_mut("enum") {
+        values.push(Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_convert_schema() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "nullable": true, "example": "Ann"},
+                "kind": {"type": "string", "enum": ["photo"]},
+                "deleted_at": {"type": "object", "nullable": true},
+                "score": {"type": "number", "minimum": 0, "exclusiveMinimum": true},
+            },
+        });
+        convert_schema(&mut schema);
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": ["string", "null"], "examples": ["Ann"]},
+                    "kind": {"type": "string", "const": "photo"},
+                    "deleted_at": {"type": "null"},
+                    "score": {"type": "number", "exclusiveMinimum": 0},
+                },
+            })
+        );
+    }
+}
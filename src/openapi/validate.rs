@@ -0,0 +1,462 @@
+use crate::http::RequestResponse;
+use har::v1_2::Entries;
+use crate::openapi::parameter::{query_value, QueryValue};
+use crate::openapi::schema::resolve_schema;
+use openapiv3 as oa;
+use openapiv3::RefOr;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// Index of the entry in the HAR log.
+    pub entry: usize,
+    /// The matched operation, e.g. `GET /users/{id}`, if any.
+    pub operation: Option<String>,
+    /// JSON pointer into the exchange, e.g. `/response/body/items/0/id` or `/request/query/page`.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "entry {}", self.entry)?;
+        if let Some(operation) = &self.operation {
+            write!(f, " ({})", operation)?;
+        }
+        write!(f, ": {}: {}", self.pointer, self.message)
+    }
+}
+
+/// Append a reference token to a JSON pointer, escaping `~` and `/`.
+fn pointer(base: &str, token: &str) -> String {
+    format!("{}/{}", base, token.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Values of path parameters by name, e.g. `[("id", "12")]` for `/users/12` and `/users/{id}`.
+pub(crate) type PathValues = Vec<(String, String)>;
+
+/// Find the path template matching `path`, preferring templates with more literal segments, so
+/// `/users/me` wins over `/users/{id}`.
+fn match_path<'a>(spec: &'a oa::OpenAPI, path: &str) -> Option<(&'a str, &'a oa::PathItem, PathValues)> {
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    spec.paths
+        .paths
+        .iter()
+        .filter_map(|(template, item)| {
+            let parts = template.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+            if parts.len() != segments.len() {
+                return None;
+            }
+            let mut params = Vec::new();
+            for (part, segment) in parts.iter().zip(&segments) {
+                if let Some(name) = part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+                    params.push((name.to_string(), urlparse::unquote(segment).unwrap_or(segment.to_string())));
+                } else if part != segment {
+                    return None;
+                }
+            }
+            Some((template.as_str(), item.as_item()?, params))
+        })
+        .max_by_key(|(_, _, params)| std::cmp::Reverse(params.len()))
+}
+
+/// Find the path template matching the path of a request URL. Paths are usually absolute, but may be
+/// relative to a server with a base path, e.g. `/api`.
+pub(crate) fn match_request<'a>(spec: &'a oa::OpenAPI, path: &str) -> Option<(&'a str, &'a oa::PathItem, PathValues)> {
+    let base = spec
+        .servers
+        .first()
+        .and_then(|s| url::Url::parse(&s.url).ok())
+        .map(|u| u.path().trim_end_matches('/').to_string())
+        .unwrap_or_default();
+    match_path(spec, path).or_else(|| match_path(spec, path.strip_prefix(base.as_str()).filter(|_| !base.is_empty())?))
+}
+
+/// The documented response for `status`: the exact code, then its range (e.g. `4XX`), then `default`.
+pub(crate) fn find_response(responses: &oa::Responses, status: u16) -> Option<&RefOr<oa::Response>> {
+    responses
+        .responses
+        .get(&oa::StatusCode::Code(status))
+        .or_else(|| responses.responses.get(&oa::StatusCode::Range(status / 100)))
+        .or(responses.default.as_ref())
+}
+
+/// Check one exchange against the operation it matches in `spec`: the path and method, path,
+/// query and header parameters, the request body, and the response body for its status code.
+pub fn validate_entry(spec: &oa::OpenAPI, entry: usize, rr: &RequestResponse) -> Vec<Violation> {
+    let mut errors = Vec::new();
+    let operation = validate_operation(spec, rr, &mut errors);
+    errors
+        .into_iter()
+        .map(|(pointer, message)| Violation {
+            entry,
+            operation: operation.clone(),
+            pointer,
+            message,
+        })
+        .collect()
+}
+
+/// Like [validate_entry], for an entry as read from the HAR file. Requests that don't name an
+/// object, e.g. `GET /`, are reported as matching no path.
+pub fn validate_har_entry(spec: &oa::OpenAPI, entry: usize, har_entry: Entries) -> Vec<Violation> {
+    let url = har_entry.request.url.clone();
+    let path = url::Url::parse(&url).map(|u| u.path().to_string()).unwrap_or(url);
+    match RequestResponse::try_from_entry(har_entry) {
+        Some(rr) => validate_entry(spec, entry, &rr),
+        None => vec![Violation {
+            entry,
+            operation: None,
+            pointer: "/request/url".to_string(),
+            message: format!("no path matches {}", path),
+        }],
+    }
+}
+
+fn validate_operation(spec: &oa::OpenAPI, rr: &RequestResponse, errors: &mut Vec<(String, String)>) -> Option<String> {
+    let path = rr.request.url.path();
+    let Some((template, item, path_values)) = match_request(spec, rr.request.url.path()) else {
+        errors.push(("/request/url".to_string(), format!("no path matches {}", path)));
+        return None;
+    };
+    let method = rr.method().to_lowercase();
+    let Some((_, op)) = item.iter().find(|(m, _)| *m == method) else {
+        errors.push(("/request/method".to_string(), format!("{} is not defined for {}", rr.method(), template)));
+        return None;
+    };
+
+    let params = item.parameters.iter().chain(&op.parameters).filter_map(|p| p.resolve(spec).ok());
+    for param in params {
+        // Headers such as `Authorization` or `Accept` are dropped when the HAR is loaded, so they can't be checked.
+        if matches!(param.kind, oa::ParameterKind::Header { .. }) && crate::http::ignore_header(&param.name) {
+            continue;
+        }
+        let (location, value) = match &param.kind {
+            oa::ParameterKind::Path { .. } => ("path", path_values.iter().find(|(n, _)| *n == param.name).map(scalar)),
+            // Query values may be spread over several keys, e.g. `tags[]=a&tags[]=b`.
+            oa::ParameterKind::Query { .. } => ("query", query_value(Some(spec), &rr.request.query, param)),
+            oa::ParameterKind::Header { .. } => ("header", rr.request.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(&param.name)).map(scalar)),
+            oa::ParameterKind::Cookie { .. } => ("cookie", rr.request.cookies.iter().find(|(n, _)| *n == param.name).map(scalar)),
+        };
+        let ptr = pointer(&format!("/request/{}", location), &param.name);
+        match (value, param.schema()) {
+            (None, _) if param.required => errors.push((ptr, "required parameter is missing".to_string())),
+            (Some(value), Some(schema)) => match resolve_schema(spec, schema) {
+                Some(schema) => validate_value(spec, schema, &coerce_parameter(spec, schema, &value), &ptr, errors),
+                None => errors.push((ptr, missing_reference(schema))),
+            },
+            _ => {}
+        }
+    }
+
+    let body = op.request_body.as_ref().and_then(|b| b.resolve(spec).ok());
+    match (body, &rr.request.body) {
+        (Some(body), None) if body.required => errors.push(("/request/body".to_string(), "required request body is missing".to_string())),
+        (Some(body), Some(request_body)) => {
+            if let Some(schema) = json_schema(&body.content, Some(&request_body.mime)) {
+                validate_ref(spec, schema, &request_body.content, "/request/body", errors);
+            }
+        }
+        (None, Some(_)) => errors.push(("/request/body".to_string(), "operation takes no request body".to_string())),
+        _ => {}
+    }
+
+    let status = rr.response.status;
+    let response = find_response(&op.responses, status).and_then(|r| r.resolve(spec).ok());
+    match response {
+        None => errors.push(("/response/status".to_string(), format!("status {} is not documented", status))),
+        Some(response) => {
+            let mime = Some(rr.response.mime.as_str()).filter(|m| !m.is_empty());
+            if let (Some(schema), false) = (json_schema(&response.content, mime), rr.response.data.is_null()) {
+                validate_ref(spec, schema, &rr.response.data, "/response/body", errors);
+            }
+        }
+    }
+    Some(format!("{} {}", rr.method().to_uppercase(), template))
+}
+
+/// The schema of the media type `mime`, or of the first JSON media type. Only JSON bodies are validated.
+pub(crate) fn json_schema<'a>(content: &'a indexmap::IndexMap<String, oa::MediaType>, mime: Option<&str>) -> Option<&'a RefOr<oa::Schema>> {
+    let mime = mime.map(|m| m.split(';').next().unwrap_or(m).trim());
+    if mime.is_some_and(|m| !m.contains("json")) {
+        return None;
+    }
+    let media = mime
+        .and_then(|m| content.get(m))
+        .or_else(|| content.iter().find(|(m, _)| m.contains("json")).map(|(_, media)| media))?;
+    media.schema.as_ref()
+}
+
+/// The value of a path, header or cookie parameter, which is never split into a list.
+fn scalar((_, value): &(String, String)) -> QueryValue<'_> {
+    QueryValue::Scalar(value)
+}
+
+/// Parameters arrive as strings; parse them as the schema's type, leaving them as strings when
+/// they don't parse so the mismatch is reported. Lists and deepObject properties are parsed
+/// item by item.
+fn coerce_parameter(spec: &oa::OpenAPI, schema: &oa::Schema, value: &QueryValue) -> Value {
+    let coerce = |schema: Option<&RefOr<oa::Schema>>, raw: &str| match schema {
+        Some(schema) => resolve_schema(spec, schema).map_or_else(|| Value::from(raw), |schema| coerce_scalar(schema, raw)),
+        None => Value::from(raw),
+    };
+    match (value, &schema.kind) {
+        (QueryValue::Scalar(raw), _) => coerce_scalar(schema, raw),
+        (QueryValue::List(items), oa::SchemaKind::Type(oa::Type::Array(a))) => {
+            Value::Array(items.iter().map(|raw| coerce(a.items.as_deref(), raw)).collect())
+        }
+        (QueryValue::List(items), _) => Value::Array(items.iter().map(|raw| Value::from(*raw)).collect()),
+        (QueryValue::Object(properties), kind) => {
+            let object = match kind {
+                oa::SchemaKind::Type(oa::Type::Object(o)) => Some(o),
+                _ => None,
+            };
+            Value::Object(
+                properties
+                    .iter()
+                    .map(|(key, raw)| (key.to_string(), coerce(object.and_then(|o| o.properties.get(*key)), raw)))
+                    .collect(),
+            )
+        }
+    }
+}
+
+fn coerce_scalar(schema: &oa::Schema, raw: &str) -> Value {
+    let parsed = match &schema.kind {
+        oa::SchemaKind::Type(oa::Type::Integer(_)) => raw.parse::<i64>().ok().map(Value::from),
+        oa::SchemaKind::Type(oa::Type::Number(_)) => raw.parse::<f64>().ok().map(Value::from),
+        oa::SchemaKind::Type(oa::Type::Boolean {}) => raw.parse::<bool>().ok().map(Value::from),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn missing_reference(schema: &RefOr<oa::Schema>) -> String {
+    format!("missing reference {}", schema.as_ref_str().unwrap_or_default())
+}
+
+/// Like [validate_value], reporting a reference to a schema that doesn't exist as a violation.
+fn validate_ref(spec: &oa::OpenAPI, schema: &RefOr<oa::Schema>, value: &Value, ptr: &str, errors: &mut Vec<(String, String)>) {
+    match resolve_schema(spec, schema) {
+        Some(schema) => validate_value(spec, schema, value, ptr, errors),
+        None => errors.push((ptr.to_string(), missing_reference(schema))),
+    }
+}
+
+/// Validate `value` against `schema`, pushing a (JSON pointer, message) pair for every violation.
+pub fn validate_value(spec: &oa::OpenAPI, schema: &oa::Schema, value: &Value, ptr: &str, errors: &mut Vec<(String, String)>) {
+    use oa::SchemaKind::Type as T;
+    if value.is_null() {
+        if !schema.data.nullable && !matches!(schema.kind, oa::SchemaKind::Any(_)) {
+            errors.push((ptr.to_string(), "unexpected null".to_string()));
+        }
+        return;
+    }
+    let mut mismatch = |expected: &str| errors.push((ptr.to_string(), format!("expected {}, found {}", expected, json_type(value))));
+    match (&schema.kind, value) {
+        (T(oa::Type::String(s)), Value::String(v)) => {
+            if !s.enumeration.is_empty() && !s.enumeration.contains(v) {
+                errors.push((ptr.to_string(), format!("{:?} is not one of {}", v, s.enumeration.join(", "))));
+            }
+        }
+        (T(oa::Type::String(_)), _) => mismatch("string"),
+        (T(oa::Type::Integer(_)), Value::Number(n)) if !n.is_f64() => {}
+        (T(oa::Type::Integer(_)), _) => mismatch("integer"),
+        (T(oa::Type::Number(_)), Value::Number(_)) => {}
+        (T(oa::Type::Number(_)), _) => mismatch("number"),
+        (T(oa::Type::Boolean {}), Value::Bool(_)) => {}
+        (T(oa::Type::Boolean {}), _) => mismatch("boolean"),
+        (T(oa::Type::Array(a)), Value::Array(items)) => {
+            if let Some(item_schema) = &a.items {
+                for (i, item) in items.iter().enumerate() {
+                    validate_ref(spec, item_schema, item, &pointer(ptr, &i.to_string()), errors);
+                }
+            }
+        }
+        (T(oa::Type::Array(_)), _) => mismatch("array"),
+        (T(oa::Type::Object(o)), Value::Object(map)) => {
+            for name in &o.required {
+                if !map.contains_key(name) {
+                    errors.push((pointer(ptr, name), "required property is missing".to_string()));
+                }
+            }
+            for (name, v) in map {
+                let ptr = pointer(ptr, name);
+                match (o.properties.get(name), &o.additional_properties) {
+                    (Some(property), _) => validate_ref(spec, property, v, &ptr, errors),
+                    (None, Some(oa::AdditionalProperties::Schema(schema))) => validate_ref(spec, schema, v, &ptr, errors),
+                    (None, Some(oa::AdditionalProperties::Any(false))) => errors.push((ptr, "unknown property".to_string())),
+                    (None, _) => {}
+                }
+            }
+        }
+        (T(oa::Type::Object(_)), _) => mismatch("object"),
+        (oa::SchemaKind::AllOf { all_of }, _) => {
+            for schema in all_of {
+                validate_ref(spec, schema, value, ptr, errors);
+            }
+        }
+        (oa::SchemaKind::OneOf { one_of: variants }, _) | (oa::SchemaKind::AnyOf { any_of: variants }, _) => {
+            let matches = variants.iter().any(|schema| {
+                let mut variant_errors = Vec::new();
+                validate_ref(spec, schema, value, ptr, &mut variant_errors);
+                variant_errors.is_empty()
+            });
+            if !matches {
+                errors.push((ptr.to_string(), "matches none of the alternatives".to_string()));
+            }
+        }
+        (oa::SchemaKind::Not { .. }, _) | (oa::SchemaKind::Any(_), _) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{test_entry, test_entry_with_headers};
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_entry() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "servers": [{"url": "https://api.example.com"}],
+            "paths": {
+                "/users/{id}": {"get": {
+                    "parameters": [
+                        {"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}},
+                        {"name": "expand", "in": "query", "schema": {"type": "boolean"}},
+                    ],
+                    "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/User"}}}}},
+                }},
+            },
+            "components": {"schemas": {
+                "User": {"type": "object", "required": ["id", "name"], "properties": {
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                }},
+            }},
+        }))
+        .unwrap();
+        let entry = test_entry("GET", "https://api.example.com/users/12?expand=yes", json!({"id": "12", "tags": ["a", 1]}));
+        let rr = RequestResponse::from(entry);
+        let violations = validate_entry(&spec, 3, &rr)
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(violations, vec![
+            "entry 3 (GET /users/{id}): /request/query/expand: expected boolean, found string",
+            "entry 3 (GET /users/{id}): /response/body/name: required property is missing",
+            "entry 3 (GET /users/{id}): /response/body/id: expected integer, found string",
+            "entry 3 (GET /users/{id}): /response/body/tags/1: expected string, found integer",
+        ]);
+
+        let entry = test_entry("GET", "https://api.example.com/teams/12", json!({}));
+        let violations = validate_entry(&spec, 0, &RequestResponse::from(entry));
+        assert_eq!(violations[0].pointer, "/request/url");
+
+        let entry = test_entry("GET", "https://api.example.com/", json!({}));
+        let violations = validate_har_entry(&spec, 1, entry).into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(violations, vec!["entry 1: /request/url: no path matches /"]);
+    }
+
+    #[test]
+    fn test_validate_dangling_reference() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/users": {"get": {
+                "parameters": [{"name": "page", "in": "query", "schema": {"$ref": "#/components/schemas/Page"}}],
+                "responses": {"200": {"description": "", "content": {"application/json": {"schema": {
+                    "type": "array", "items": {"$ref": "#/components/schemas/User"},
+                }}}}},
+            }}},
+        }))
+        .unwrap();
+        let entry = test_entry("GET", "https://api.example.com/users?page=2", json!([{"id": 1}]));
+        let violations = validate_entry(&spec, 0, &RequestResponse::from(entry))
+            .into_iter()
+            .map(|v| (v.pointer, v.message))
+            .collect::<Vec<_>>();
+        assert_eq!(violations, vec![
+            ("/request/query/page".to_string(), "missing reference #/components/schemas/Page".to_string()),
+            ("/response/body/0".to_string(), "missing reference #/components/schemas/User".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_validate_query_styles() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/items": {"get": {
+                "parameters": [
+                    {"name": "id", "in": "query", "required": true, "schema": {"type": "array", "items": {"type": "integer"}}},
+                    {"name": "tags", "in": "query", "style": "pipeDelimited", "explode": false, "schema": {"type": "array", "items": {"type": "string"}}},
+                    {"name": "filter", "in": "query", "required": true, "style": "deepObject", "schema": {
+                        "type": "object", "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+                    }},
+                ],
+                "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"type": "array"}}}}},
+            }}},
+        }))
+        .unwrap();
+        let url = "https://api.example.com/items?id=1&id=2&tags=a|b&filter[name]=a&filter[age]=3";
+        let mut entry = test_entry("GET", url, json!([]));
+        assert!(validate_entry(&spec, 0, &RequestResponse::from(entry.clone())).is_empty());
+
+        entry.response.content.mime_type = Some("text/html".to_string());
+        entry.response.content.text = Some("<html></html>".to_string());
+        assert!(validate_entry(&spec, 0, &RequestResponse::from(entry)).is_empty());
+
+        let entry = test_entry("GET", "https://api.example.com/items?id=1&id=x&filter[age]=old", json!([]));
+        let violations = validate_entry(&spec, 0, &RequestResponse::from(entry))
+            .into_iter()
+            .map(|v| (v.pointer, v.message))
+            .collect::<Vec<_>>();
+        assert_eq!(violations, vec![
+            ("/request/query/id/1".to_string(), "expected integer, found string".to_string()),
+            ("/request/query/filter/age".to_string(), "expected integer, found string".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_validate_ignored_headers() {
+        let spec: oa::OpenAPI = serde_json::from_value(json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Test", "version": "1"},
+            "paths": {"/users": {"get": {
+                "parameters": [
+                    {"name": "Authorization", "in": "header", "required": true, "schema": {"type": "string"}},
+                    {"name": "Accept", "in": "header", "required": true, "schema": {"type": "string"}},
+                    {"name": "X-Tenant", "in": "header", "required": true, "schema": {"type": "integer"}},
+                ],
+                "responses": {"200": {"description": "", "content": {"application/json": {"schema": {"type": "array"}}}}},
+            }}},
+        }))
+        .unwrap();
+        let headers = [("Authorization", "Bearer abc"), ("Accept", "application/json")];
+        let entry = test_entry_with_headers("GET", "https://api.example.com/users", json!([]), &headers, &[]);
+        let violations = validate_entry(&spec, 0, &RequestResponse::from(entry))
+            .into_iter()
+            .map(|v| (v.pointer, v.message))
+            .collect::<Vec<_>>();
+        assert_eq!(violations, vec![("/request/header/X-Tenant".to_string(), "required parameter is missing".to_string())]);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::http::{parse_cookie_header, Header};
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use har::v1_2::{Creator, Entries, Log};
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// One exchange passing through the recording proxy.
+#[derive(Debug)]
+pub struct Exchange {
+    pub started: SystemTime,
+    pub elapsed: Duration,
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<Header>,
+    pub request_body: Vec<u8>,
+    pub status: u16,
+    pub status_text: String,
+    pub response_headers: Vec<Header>,
+    pub response_body: Vec<u8>,
+}
+
+fn find_header<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+fn har_headers(headers: &[Header]) -> Vec<serde_json::Value> {
+    headers.iter().map(|(name, value)| json!({"name": name, "value": value})).collect()
+}
+
+/// Format a time as an ISO 8601 UTC timestamp with milliseconds, e.g. `2023-08-01T12:00:00.000Z`.
+pub fn iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The HAR `content` of a response. Bodies that aren't UTF-8, e.g. images, are base64 encoded.
+fn response_content(headers: &[Header], body: Vec<u8>) -> serde_json::Value {
+    let size = body.len();
+    let mime = find_header(headers, "content-type").unwrap_or_default();
+    match String::from_utf8(body) {
+        Ok(text) => json!({"size": size, "mimeType": mime, "text": text}),
+        Err(e) => json!({"size": size, "mimeType": mime, "text": BASE64.encode(e.into_bytes()), "encoding": "base64"}),
+    }
+}
+
+impl Exchange {
+    /// A HAR 1.2 entry for the exchange. Request bodies that aren't UTF-8 are recorded by size only.
+    pub fn into_entry(self) -> Result<Entries> {
+        let query = Url::parse(&self.url)?
+            .query_pairs()
+            .map(|(name, value)| json!({"name": name, "value": value}))
+            .collect::<Vec<_>>();
+        let cookies = find_header(&self.request_headers, "cookie")
+            .map(parse_cookie_header)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, value)| json!({"name": name, "value": value}))
+            .collect::<Vec<_>>();
+        let body_size = self.request_body.len();
+        let post_data = (body_size > 0).then(|| {
+            json!({
+                "mimeType": find_header(&self.request_headers, "content-type").unwrap_or_default(),
+                "text": String::from_utf8(self.request_body).ok(),
+            })
+        });
+        let elapsed = self.elapsed.as_secs_f64() * 1000.0;
+        let entry = json!({
+            "startedDateTime": iso8601(self.started),
+            "time": elapsed,
+            "request": {
+                "method": self.method,
+                "url": self.url,
+                "httpVersion": "HTTP/1.1",
+                "cookies": cookies,
+                "headers": har_headers(&self.request_headers),
+                "queryString": query,
+                "postData": post_data,
+                "headersSize": -1,
+                "bodySize": body_size,
+            },
+            "response": {
+                "status": self.status,
+                "statusText": self.status_text,
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": har_headers(&self.response_headers),
+                "content": response_content(&self.response_headers, self.response_body),
+                "redirectURL": find_header(&self.response_headers, "location").unwrap_or_default(),
+                "headersSize": -1,
+                "bodySize": -1,
+            },
+            "cache": {},
+            "timings": {"send": 0.0, "wait": elapsed, "receive": 0.0},
+        });
+        Ok(serde_json::from_value(entry)?)
+    }
+}
+
+/// Write `entries` to `path` as a HAR 1.2 file that [crate::http::read_har] can read.
+pub fn write_har(path: &str, entries: &[Entries]) -> Result<()> {
+    let log = Log {
+        creator: Creator {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            comment: None,
+        },
+        browser: None,
+        pages: None,
+        entries: entries.to_vec(),
+        comment: None,
+    };
+    let har = har::Har {
+        log: har::Spec::V1_2(log),
+    };
+    std::fs::write(path, har::to_json(&har)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{read_har, RequestResponse};
+
+    #[test]
+    fn test_iso8601() {
+        assert_eq!(iso8601(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso8601(UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)), "2024-02-29T12:34:56.789Z");
+    }
+
+    #[test]
+    fn test_write_har_round_trip() -> Result<()> {
+        let exchange = Exchange {
+            started: UNIX_EPOCH + Duration::from_secs(1_690_891_200),
+            elapsed: Duration::from_millis(42),
+            method: "POST".to_string(),
+            url: "https://api.example.com/users?notify=true".to_string(),
+            request_headers: vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Cookie".to_string(), "sessionid=abc".to_string()),
+            ],
+            request_body: br#"{"name": "Ann"}"#.to_vec(),
+            status: 201,
+            status_text: "Created".to_string(),
+            response_headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            response_body: br#"{"id": 1, "name": "Ann"}"#.to_vec(),
+        };
+        let path = std::env::temp_dir().join(format!("har2oa-record-{}.har", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_har(path, &[exchange.into_entry()?])?;
+        let entries = read_har(path)?;
+        std::fs::remove_file(path)?;
+        assert_eq!(entries[0].started_date_time, "2023-08-01T12:00:00.000Z");
+        let rr = RequestResponse::from(entries.into_iter().next().unwrap());
+        assert_eq!(rr.response.status, 201);
+        assert_eq!(rr.response.data, json!({"id": 1, "name": "Ann"}));
+        assert_eq!(rr.request.body.unwrap().content, json!({"name": "Ann"}));
+        assert_eq!(rr.request.query, vec![("notify".to_string(), "true".to_string())]);
+        assert_eq!(rr.request.cookies, vec![("sessionid".to_string(), "abc".to_string())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_request_body() -> Result<()> {
+        let exchange = Exchange {
+            started: UNIX_EPOCH,
+            elapsed: Duration::ZERO,
+            method: "PUT".to_string(),
+            url: "https://api.example.com/avatar".to_string(),
+            request_headers: vec![("Content-Type".to_string(), "image/png".to_string())],
+            request_body: vec![0x89, b'P', b'N', b'G', 0xff],
+            status: 204,
+            status_text: "No Content".to_string(),
+            response_headers: Vec::new(),
+            response_body: Vec::new(),
+        };
+        let entry = exchange.into_entry()?;
+        assert_eq!(entry.request.body_size, 5);
+        assert_eq!(entry.request.post_data.unwrap().text, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_response_body() -> Result<()> {
+        let exchange = Exchange {
+            started: UNIX_EPOCH,
+            elapsed: Duration::ZERO,
+            method: "GET".to_string(),
+            url: "https://api.example.com/avatar".to_string(),
+            request_headers: Vec::new(),
+            request_body: Vec::new(),
+            status: 200,
+            status_text: "OK".to_string(),
+            response_headers: vec![("Content-Type".to_string(), "image/png".to_string())],
+            response_body: vec![0x89, b'P', b'N', b'G', 0xff],
+        };
+        let content = exchange.into_entry()?.response.content;
+        assert_eq!(content.size, 5);
+        assert_eq!(content.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(content.encoding.as_deref(), Some("base64"));
+        assert_eq!(content.text.as_deref(), Some("iVBOR/8="));
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::http::Response;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Keys (compared case-insensitively) whose values differ between runs or environments, and
+/// are left out of comparisons, in addition to any given with `--ignore`.
+const VOLATILE_KEYS: [&str; 10] = [
+    "created_at",
+    "createdat",
+    "updated_at",
+    "updatedat",
+    "timestamp",
+    "request_id",
+    "requestid",
+    "trace_id",
+    "traceid",
+    "etag",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Difference {
+    /// JSON pointer into the response, e.g. `/body/items/0/id` or `/status`.
+    pub pointer: String,
+    pub message: String,
+    /// Whether a client relying on the recorded response could break.
+    pub regression: bool,
+}
+
+/// Compares a replayed response with the recorded one by shape: the status, and for JSON
+/// bodies the properties and their types, but not their values.
+#[derive(Debug, Clone, Default)]
+pub struct Comparer {
+    ignore: Vec<String>,
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Append a key to a JSON pointer, escaping `~` and `/`.
+fn child(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn difference(pointer: String, message: &str, regression: bool) -> Difference {
+    Difference {
+        pointer,
+        message: message.to_string(),
+        regression,
+    }
+}
+
+impl Comparer {
+    pub fn new(ignore: &[String]) -> Self {
+        Self {
+            ignore: ignore.iter().map(|k| k.to_lowercase()).collect(),
+        }
+    }
+
+    fn is_ignored(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        VOLATILE_KEYS.contains(&key.as_str()) || self.ignore.contains(&key)
+    }
+
+    pub fn compare(&self, recorded: &Response, actual: &Response) -> Vec<Difference> {
+        let mut out = Vec::new();
+        if recorded.status != actual.status {
+            let message = format!("status {} became {}", recorded.status, actual.status);
+            out.push(difference("/status".to_string(), &message, true));
+            return out;
+        }
+        self.compare_value(&recorded.data, &actual.data, "/body", &mut out);
+        out
+    }
+
+    fn compare_value(&self, recorded: &Value, actual: &Value, pointer: &str, out: &mut Vec<Difference>) {
+        match (recorded, actual) {
+            // Nullable fields are null in some responses and not in others.
+            (Value::Null, _) | (_, Value::Null) => {}
+            // Non-JSON bodies (HTML, text) are compared by status only.
+            (Value::String(_), Value::String(_)) => {}
+            (Value::Object(recorded), Value::Object(actual)) => {
+                for (key, value) in recorded {
+                    if self.is_ignored(key) {
+                        continue;
+                    }
+                    let pointer = child(pointer, key);
+                    match actual.get(key) {
+                        Some(actual) => self.compare_value(value, actual, &pointer, out),
+                        None => out.push(difference(pointer, "property is missing", true)),
+                    }
+                }
+                for key in actual.keys().filter(|k| !recorded.contains_key(*k) && !self.is_ignored(k)) {
+                    let pointer = child(pointer, key);
+                    out.push(difference(pointer, "new property", false));
+                }
+            }
+            (Value::Array(recorded), Value::Array(actual)) => {
+                // Items are compared by shape, so the first of each stands in for the rest.
+                if let (Some(recorded), Some(actual)) = (recorded.first(), actual.first()) {
+                    self.compare_value(recorded, actual, &format!("{}/0", pointer), out);
+                }
+            }
+            (recorded, actual) if json_type(recorded) != json_type(actual) => {
+                let message = format!("{} became {}", json_type(recorded), json_type(actual));
+                out.push(difference(pointer.to_string(), &message, true));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, data: Value) -> Response {
+        Response {
+            status,
+            data,
+            mime: "application/json".to_string(),
+            headers: Vec::new(),
+            set_cookies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let comparer = Comparer::new(&["version".to_string()]);
+        let recorded = response(200, json!({
+            "id": 1,
+            "name": "Ann",
+            "team": {"id": 2, "slug": "core"},
+            "tags": [{"name": "a"}],
+            "updated_at": "2023-08-01T12:00:00Z",
+            "version": 3,
+        }));
+        let actual = response(200, json!({
+            "id": "1",
+            "name": "Bob",
+            "team": {"id": 2},
+            "tags": [{"name": "b", "color": "red"}],
+            "version": "3",
+            "avatar": null,
+        }));
+        let differences = comparer
+            .compare(&recorded, &actual)
+            .into_iter()
+            .map(|d| (d.pointer, d.regression))
+            .collect::<Vec<_>>();
+        assert_eq!(differences, vec![
+            ("/body/id".to_string(), true),
+            ("/body/team/slug".to_string(), true),
+            ("/body/tags/0/color".to_string(), false),
+            ("/body/avatar".to_string(), false),
+        ]);
+        assert_eq!(comparer.compare(&recorded, &response(500, Value::Null))[0].message, "status 200 became 500");
+    }
+}